@@ -1,18 +1,29 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use tauri::{Emitter, Manager};
 
-use crate::types::{IndexStatus, Note, NoteMetadata, SearchResult};
+use crate::embeddings::Priority;
+use crate::error::{AppError, ErrorLogEntry};
+use crate::links::LinkGraph;
+use crate::types::{
+    DbCandidate, DuplicateGroup, DuplicateOptions, HistoryEntry, IndexPhase, IndexStatus,
+    MaintenanceStatus, Note, NoteMetadata, NoteSuggestion, RankingWeights, ResourceLimits,
+    RevisionMetadata, SavedSearch, SavedSearchAlert, ScoreExplanation, SearchExample,
+    SearchOptions, SearchResult, Settings, TopicCluster, WatcherStatus,
+};
 use crate::AppMutex;
 
 // ─── Tauri commands ────────────────────────────────────────────────────────────
 
-/// Try to auto-detect the Joplin SQLite path. Returns None if not found.
-/// Frontend uses this to pre-fill the path or prompt user to browse.
+/// List every Joplin database found on this machine (all profiles, native and
+/// sandboxed installs), best candidate first. Empty if none were found.
+/// Frontend uses this to auto-select a single match or let the user choose.
 #[tauri::command]
-pub async fn detect_db_path() -> Option<String> {
-    crate::db::detect_joplin_db_path().map(|p| p.to_string_lossy().to_string())
+pub async fn detect_db_path() -> Vec<DbCandidate> {
+    tokio::task::spawn_blocking(crate::db::detect_joplin_db_candidates)
+        .await
+        .unwrap_or_default()
 }
 
 /// Set the Joplin DB path manually (user browsed to it) and trigger full indexing.
@@ -21,13 +32,23 @@ pub async fn set_joplin_db_path(
     path: String,
     state: tauri::State<'_, AppMutex>,
     app: tauri::AppHandle,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     {
         let mut s = state.lock().await;
+        let mut settings = s.settings.clone();
+        settings.db_path = Some(path.clone());
+        crate::settings::save(&settings, &settings_file_path(&app)).map_err(AppError::storage)?;
+        s.settings = settings;
         s.db_path = Some(path);
         s.index_status.is_ready = false;
         s.index_status.indexed_notes = 0;
+        // Revisions belong to the previous database; rebuilt on the next pass.
+        s.revision_index = None;
+        s.revision_cache.clear();
+        s.revision_fingerprint = (0, 0);
     }
+    // Starts the watcher if no DB was auto-detected, else points it at the new path.
+    crate::watcher::start_watcher(app.clone()).await;
     let app = app.clone();
     tauri::async_runtime::spawn(async move {
         run_full_indexing(app).await;
@@ -35,78 +56,473 @@ pub async fn set_joplin_db_path(
     Ok(())
 }
 
-/// Semantic search. Returns up to 10 results ranked by similarity.
-/// Returns an error string if the index is not yet ready.
+/// Semantic search. Returns up to 25 results ranked by similarity, optionally
+/// filtered and re-sorted by note metadata (`options`).
+/// Fails with `AppError::IndexNotReady` if the index is not yet ready.
 #[tauri::command]
 pub async fn search_notes(
     query: String,
+    options: Option<SearchOptions>,
     state: tauri::State<'_, AppMutex>,
-) -> Result<Vec<SearchResult>, String> {
-    // Clone Arc pointers + snapshot the cache and tombstones while holding the
-    // lock, then release the lock before the expensive ML inference.
-    let (pipeline, index_arc, cache_snapshot, tombstones) = {
-        let s = state.lock().await;
+    app: tauri::AppHandle,
+) -> Result<Vec<SearchResult>, AppError> {
+    let options = options.unwrap_or_default();
+    let snapshot = SearchSnapshot::take(&state).await?;
+    let query_embedding = snapshot.pipeline.embed_one(&query).await.map_err(AppError::inference)?;
+    let results = run_search(snapshot, &query_embedding, &query, &options, &HashSet::new()).await?;
+    record_query(&app, &query, &options).await;
+    Ok(results)
+}
+
+/// Query-by-example: find notes similar to a pasted passage and/or a set of
+/// seed notes, optionally pushed away from negative examples. Long text is
+/// split into passages whose embeddings are averaged; seed notes contribute
+/// their stored vectors. Seed and negative notes are left out of the results.
+#[tauri::command]
+pub async fn search_by_example(
+    example: SearchExample,
+    options: Option<SearchOptions>,
+    state: tauri::State<'_, AppMutex>,
+) -> Result<Vec<SearchResult>, AppError> {
+    let options = options.unwrap_or_default();
+    let snapshot = SearchSnapshot::take(&state).await?;
+
+    // Embed the example texts before touching the index, so no lock is held
+    // across inference; the read lock only covers copying the seed vectors.
+    let positive_text = text_vector(example.text.as_deref(), &snapshot.pipeline).await?;
+    let negative_text = text_vector(example.negative_text.as_deref(), &snapshot.pipeline).await?;
+    let (positive_notes, negative_notes) =
+        if example.note_ids.is_empty() && example.negative_note_ids.is_empty() {
+            (Vec::new(), Vec::new())
+        } else {
+            let index = snapshot.index.read().await;
+            let seeds = |ids: &[String]| {
+                ids.iter()
+                    .map(|id| index.vector(id).map(<[f32]>::to_vec).ok_or(AppError::NoteNotFound))
+                    .collect::<Result<Vec<_>, _>>()
+            };
+            (seeds(&example.note_ids)?, seeds(&example.negative_note_ids)?)
+        };
+    let positive = example_vector(positive_text, positive_notes);
+    let negative = example_vector(negative_text, negative_notes);
+    let mut query_embedding = positive.ok_or(AppError::EmptyExample)?;
+    if let Some(negative) = negative {
+        // Rocchio-style: move the query away from the negative centroid.
+        const NEGATIVE_WEIGHT: f32 = 0.5;
+        for (q, n) in query_embedding.iter_mut().zip(negative) {
+            *q -= NEGATIVE_WEIGHT * n;
+        }
+        crate::index::normalize(&mut query_embedding);
+    }
+
+    let exclude: HashSet<String> = example
+        .note_ids
+        .iter()
+        .chain(&example.negative_note_ids)
+        .cloned()
+        .collect();
+    let query = example.text.unwrap_or_default();
+    run_search(snapshot, &query_embedding, &query, &options, &exclude).await
+}
+
+/// Pooled embedding of the passages of `text`. None if there is no text.
+async fn text_vector(
+    text: Option<&str>,
+    pipeline: &crate::embeddings::EmbeddingPipeline,
+) -> Result<Option<Vec<f32>>, AppError> {
+    const MAX_PASSAGES: usize = 64;
+    let Some(text) = text.filter(|t| !t.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let passages =
+        crate::preprocess::passages(&crate::preprocess::markdown_to_text(text), MAX_PASSAGES);
+    let refs: Vec<&str> = passages.iter().map(|p| p.as_str()).collect();
+    let embeddings = pipeline
+        .embed_batch(&refs, Priority::Interactive)
+        .await
+        .map_err(AppError::inference)?;
+    Ok(mean_vector(&embeddings))
+}
+
+/// Unit-length mean of the text embedding and the stored vectors of the
+/// example notes. None if there is nothing to average.
+fn example_vector(text: Option<Vec<f32>>, mut notes: Vec<Vec<f32>>) -> Option<Vec<f32>> {
+    notes.extend(text);
+    mean_vector(&notes)
+}
+
+/// Unit-length mean of unit-length copies of `vectors`. None if empty.
+fn mean_vector(vectors: &[Vec<f32>]) -> Option<Vec<f32>> {
+    let first = vectors.first()?;
+    let mut sum = vec![0.0f32; first.len()];
+    for v in vectors {
+        let mut v = v.clone();
+        crate::index::normalize(&mut v);
+        sum.iter_mut().zip(v).for_each(|(s, x)| *s += x);
+    }
+    crate::index::normalize(&mut sum);
+    Some(sum)
+}
+
+/// Shared handle to an HNSW index, as held in `AppState`.
+type SharedIndex = Arc<tokio::sync::RwLock<crate::index::SearchIndex>>;
+
+/// Everything a search needs, cloned out of `AppState` so the lock is
+/// released before the expensive ML inference.
+struct SearchSnapshot {
+    pipeline: Arc<crate::embeddings::EmbeddingPipeline>,
+    index: SharedIndex,
+    notes: HashMap<String, NoteMetadata>,
+    tombstones: HashSet<String>,
+    revisions: Option<(SharedIndex, HashMap<String, RevisionMetadata>)>,
+    link_graph: Option<LinkGraph>,
+    ranking: Option<(RankingWeights, crate::usage::UsageData)>,
+    db_path: Option<String>,
+    scope: crate::db::NoteScope,
+}
+
+impl SearchSnapshot {
+    async fn take(state: &AppMutex) -> Result<Self, AppError> {
+        let mut s = state.lock().await;
         if !s.index_status.is_ready {
-            return Err("index_not_ready".to_string());
+            return Err(AppError::IndexNotReady);
         }
-        let pipeline = s.embedding_pipeline.clone().ok_or("model_not_loaded")?;
-        let index = s.search_index.clone().ok_or("index_not_ready")?;
-        let cache_snapshot = s.note_cache.clone();
-        let tombstones = s.deleted_note_ids.clone();
-        (pipeline, index, cache_snapshot, tombstones)
-    }; // lock released here
+        s.last_activity = std::time::Instant::now();
+        Ok(Self {
+            pipeline: s.embedding_pipeline.clone().ok_or(AppError::ModelNotLoaded)?,
+            index: s.search_index.clone().ok_or(AppError::IndexNotReady)?,
+            notes: s.note_cache.clone(),
+            tombstones: s.deleted_note_ids.clone(),
+            revisions: match (&s.revision_index, s.settings.index_revisions) {
+                (Some(arc), true) => Some((arc.clone(), s.revision_cache.clone())),
+                _ => None,
+            },
+            link_graph: s.settings.boost_linked_results.then(|| s.link_graph.clone()),
+            ranking: s
+                .settings
+                .ranking
+                .is_active()
+                .then(|| (s.settings.ranking.clone(), s.usage.clone())),
+            db_path: s.db_path.clone(),
+            scope: crate::db::NoteScope::from(&s.settings),
+        })
+    }
+}
+
+/// Rank notes against `query_embedding`, applying everything in `options`.
+/// `query` is only used for explanations. Notes in `exclude` are skipped.
+async fn run_search(
+    snapshot: SearchSnapshot,
+    query_embedding: &[f32],
+    query: &str,
+    options: &SearchOptions,
+    exclude: &HashSet<String>,
+) -> Result<Vec<SearchResult>, AppError> {
+    let SearchSnapshot {
+        pipeline,
+        index: index_arc,
+        notes: cache_snapshot,
+        tombstones,
+        revisions,
+        link_graph,
+        ranking,
+        db_path,
+        scope,
+    } = snapshot;
+
+    // Filters, re-ranking and diversification need a deeper candidate pool, so
+    // that enough results survive filtering and lower-similarity notes can rise.
+    let deep = options.has_filters() || ranking.is_some() || options.mmr_lambda.is_some();
+    let top_k = if deep {
+        crate::index::FILTERED_TOP_K
+    } else {
+        crate::index::DEFAULT_TOP_K
+    } + exclude.len(); // excluded notes would otherwise take result slots
 
-    let query_embedding = pipeline.embed_one(&query).map_err(|e| e.to_string())?;
     let index = index_arc.read().await;
     let hits = index
-        .search(&query_embedding, crate::index::DEFAULT_TOP_K)
-        .map_err(|e| e.to_string())?;
+        .search(query_embedding, top_k)
+        .map_err(AppError::internal)?;
     drop(index);
 
     const MIN_SCORE: f32 = 0.30;
-    // Deduplicate by note_id: HNSW may have multiple nodes for the same note
-    // if it was edited/restored between full rebuilds. Keep the first (highest-score) hit.
-    let mut seen_ids = std::collections::HashSet::new();
-    let results: Vec<SearchResult> = hits
+    // One result per note: several of its past revisions may match too, and
+    // they only count when its current text didn't.
+    let mut seen_ids = HashSet::new();
+    let explanation = |source: &str, hit: &crate::index::IndexResult, rank: usize| {
+        options.explain.then(|| ScoreExplanation {
+            source: source.to_string(),
+            cosine_distance: hit.distance,
+            rank_raw: rank + 1,
+            ..Default::default()
+        })
+    };
+    let mut results: Vec<SearchResult> = hits
         .into_iter()
-        .filter(|hit| !tombstones.contains(&hit.note_id))
-        .filter_map(|hit| {
+        .enumerate()
+        .filter(|(_, hit)| !tombstones.contains(&hit.note_id) && !exclude.contains(&hit.note_id))
+        .filter_map(|(rank, hit)| {
             cache_snapshot.get(&hit.note_id).map(|meta| SearchResult {
                 note: meta.clone(),
                 score: hit.score,
+                revision: None,
+                ranking: None,
+                explanation: explanation("note", &hit, rank),
             })
         })
+        .filter(|r| options.matches(&r.note))
         .filter(|r| r.score >= MIN_SCORE)
         .filter(|r| seen_ids.insert(r.note.id.clone()))
         .collect();
 
+    // Past revisions: only surface notes whose current content did not match,
+    // so the result reads as "this text existed in note X on date Y".
+    if let Some((revision_index, revision_cache)) = &revisions {
+        let index = revision_index.read().await;
+        let revision_hits = index
+            .search(query_embedding, top_k)
+            .map_err(AppError::internal)?;
+        drop(index);
+
+        results.extend(
+            revision_hits
+                .into_iter()
+                .enumerate()
+                .filter_map(|(rank, hit)| {
+                    let revision = revision_cache.get(&hit.note_id)?;
+                    let note_id = &revision.note_id;
+                    if tombstones.contains(note_id) || exclude.contains(note_id) {
+                        return None;
+                    }
+                    cache_snapshot.get(&revision.note_id).map(|meta| SearchResult {
+                        note: meta.clone(),
+                        score: hit.score,
+                        revision: Some(revision.clone()),
+                        ranking: None,
+                        explanation: explanation("revision", &hit, rank),
+                    })
+                })
+                .filter(|r| options.matches(&r.note))
+                .filter(|r| r.score >= MIN_SCORE)
+                .filter(|r| seen_ids.insert(r.note.id.clone())),
+        );
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    }
+
+    if options.explain {
+        let mut filters = vec!["not deleted".to_string()];
+        filters.extend(options.describe_filters());
+        filters.push(format!("min_score >= {MIN_SCORE:.2}"));
+        filters.push("one result per note".to_string());
+        for (rank, r) in results.iter_mut().enumerate() {
+            if let Some(e) = &mut r.explanation {
+                e.rank_filtered = rank + 1;
+                e.filters = filters.clone();
+            }
+        }
+    }
+
+    if let Some((weights, usage)) = &ranking {
+        crate::search::apply_ranking(&mut results, weights, usage, crate::usage::now_ms());
+    }
+    if let Some(graph) = &link_graph {
+        crate::search::boost_linked(&mut results, graph);
+    }
+
+    if let Some(lambda) = options.mmr_lambda {
+        // Revision hits are compared by the embedding of that revision.
+        // Only the candidates' vectors are copied out.
+        let vectors: Vec<Option<Vec<f32>>> = {
+            let index = index_arc.read().await;
+            let revision_index = match &revisions {
+                Some((arc, _)) => Some(arc.read().await),
+                None => None,
+            };
+            results
+                .iter()
+                .map(|r| match &r.revision {
+                    Some(rev) => revision_index.as_ref().and_then(|i| i.vector(&rev.id)),
+                    None => index.vector(&r.note.id),
+                })
+                .map(|v| v.map(<[f32]>::to_vec))
+                .collect()
+        };
+        results = crate::search::diversify(results, &vectors, lambda, crate::index::DEFAULT_TOP_K);
+    }
+
+    results.truncate(crate::index::DEFAULT_TOP_K);
+    crate::search::sort_results(&mut results, options);
+
+    if options.explain {
+        for (rank, r) in results.iter_mut().enumerate() {
+            if let Some(e) = &mut r.explanation {
+                e.rank_final = rank + 1;
+            }
+        }
+        if let Some(db_path) = db_path {
+            explain_passages(&mut results, query, query_embedding, &pipeline, &db_path, scope)
+                .await;
+        }
+    }
     Ok(results)
 }
 
+/// Fill in the lexical score of each explained note result, and the
+/// best-matching passage of the top few. Best-effort: results whose note
+/// can't be read keep None.
+async fn explain_passages(
+    results: &mut [SearchResult],
+    query: &str,
+    query_embedding: &[f32],
+    pipeline: &crate::embeddings::EmbeddingPipeline,
+    db_path: &str,
+    scope: crate::db::NoteScope,
+) {
+    /// Note results whose passages are embedded, best first.
+    const PASSAGE_NOTES: usize = 5;
+    /// Passages per note, so all of them fit in one embedding batch.
+    const MAX_PASSAGES: usize = 8;
+
+    let ids: Vec<String> = results
+        .iter()
+        .filter(|r| r.revision.is_none() && r.explanation.is_some())
+        .map(|r| r.note.id.clone())
+        .collect();
+    let (db_path, query_text) = (db_path.to_string(), query.to_string());
+    // Reading and splitting the notes blocks; keep it off the async runtime.
+    let read = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+        let conn = crate::db::open_joplin_db(&db_path)?;
+        let schema = crate::db::read_schema(&conn)?;
+        let notes = crate::db::get_notes_by_ids(&conn, &schema, scope, &ids)?;
+        let mut texts: HashMap<String, String> = notes
+            .into_iter()
+            .map(|n| {
+                let text =
+                    crate::preprocess::embedding_text(&n.title, &n.body, n.markup_language);
+                (n.id, text)
+            })
+            .collect();
+        let explained: Vec<(String, Option<f32>, Vec<String>)> = ids
+            .into_iter()
+            .filter_map(|id| Some((texts.remove(&id)?, id)))
+            .enumerate()
+            .map(|(rank, (text, id))| {
+                let passages = if rank < PASSAGE_NOTES {
+                    crate::preprocess::passages(&text, MAX_PASSAGES)
+                } else {
+                    Vec::new()
+                };
+                (id, crate::search::lexical_score(&query_text, &text), passages)
+            })
+            .collect();
+        Ok(explained)
+    });
+    let Ok(Ok(explained)) = read.await else {
+        return;
+    };
+
+    let refs: Vec<&str> = explained
+        .iter()
+        .flat_map(|(_, _, passages)| passages.iter().map(|p| p.as_str()))
+        .collect();
+    let embeddings = if refs.is_empty() {
+        Vec::new()
+    } else {
+        pipeline.embed_batch(&refs, Priority::Interactive).await.unwrap_or_default()
+    };
+
+    let mut embeddings = embeddings.into_iter();
+    for (id, lexical_score, passages) in explained {
+        let Some(explanation) = results
+            .iter_mut()
+            .find(|r| r.revision.is_none() && r.note.id == id)
+            .and_then(|r| r.explanation.as_mut())
+        else {
+            continue;
+        };
+        explanation.lexical_score = lexical_score;
+        let best = passages
+            .into_iter()
+            .zip(embeddings.by_ref())
+            .map(|(p, e)| (p, crate::index::cosine_similarity(query_embedding, &e)))
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        if let Some((passage, similarity)) = best {
+            explanation.matched_passage = Some(passage);
+            explanation.passage_similarity = Some(similarity);
+        }
+    }
+}
+
 /// Current indexing status — polled by the frontend status indicator.
 #[tauri::command]
 pub async fn get_index_status(
     state: tauri::State<'_, AppMutex>,
-) -> Result<IndexStatus, String> {
+) -> Result<IndexStatus, AppError> {
     Ok(state.lock().await.index_status.clone())
 }
 
+/// Current file watcher state. Changes are also pushed as `watcher-status` events.
+#[tauri::command]
+pub async fn get_watcher_status(
+    state: tauri::State<'_, AppMutex>,
+) -> Result<WatcherStatus, AppError> {
+    Ok(state.lock().await.watcher_status.clone())
+}
+
+/// Index health and background maintenance state. Changes are also pushed as
+/// `maintenance-status` events.
+#[tauri::command]
+pub async fn get_maintenance_status(
+    state: tauri::State<'_, AppMutex>,
+) -> Result<MaintenanceStatus, AppError> {
+    Ok(state.lock().await.maintenance_status.clone())
+}
+
+/// Recent failures of background work (indexing, delta updates, revisions,
+/// maintenance, the watcher), newest first. Each is also pushed as a
+/// `background-error` event when it happens.
+#[tauri::command]
+pub async fn get_error_log(
+    state: tauri::State<'_, AppMutex>,
+) -> Result<Vec<ErrorLogEntry>, AppError> {
+    Ok(state.lock().await.error_log.iter().rev().cloned().collect())
+}
+
+/// Forget all recorded background failures.
+#[tauri::command]
+pub async fn clear_error_log(state: tauri::State<'_, AppMutex>) -> Result<(), AppError> {
+    state.lock().await.error_log.clear();
+    Ok(())
+}
+
+/// Run whatever maintenance the index needs now, even if paused or in use.
+/// Returns the task that ran ("compact", "reembed", "rebuild"), if any.
+#[tauri::command]
+pub async fn run_maintenance(app: tauri::AppHandle) -> Result<Option<String>, AppError> {
+    let task = crate::maintenance::run(&app, true).await?;
+    Ok(task.map(|t| t.name().to_string()))
+}
+
 /// Fetch the full note (including body) by ID. Called when user selects a result.
+/// Counts as an open for the usage ranking signal.
 #[tauri::command]
 pub async fn get_note(
     id: String,
     state: tauri::State<'_, AppMutex>,
-) -> Result<Note, String> {
-    let db_path = state
-        .lock()
-        .await
-        .db_path
-        .clone()
-        .ok_or("db_not_configured")?;
-    let conn = crate::db::open_joplin_db(&db_path).map_err(|e| e.to_string())?;
-    crate::db::get_note_by_id(&conn, &id)
-        .map_err(|e| e.to_string())?
-        .ok_or_else(|| "note_not_found".to_string())
+    app: tauri::AppHandle,
+) -> Result<Note, AppError> {
+    let (db_path, scope) = {
+        let s = state.lock().await;
+        let db_path = s.db_path.clone().ok_or(AppError::DbNotConfigured)?;
+        (db_path, crate::db::NoteScope::from(&s.settings))
+    };
+    let conn = crate::db::open_joplin_db(&db_path).map_err(AppError::database)?;
+    let schema = crate::db::read_schema(&conn).map_err(AppError::database)?;
+    let note = crate::db::get_note_by_id(&conn, &schema, scope, &id)
+        .map_err(AppError::database)?
+        .ok_or(AppError::NoteNotFound)?;
+    record_open(&app, &id).await;
+    Ok(note)
 }
 
 /// Refresh: run a delta update to catch any notes added/edited/deleted since the
@@ -115,27 +531,336 @@ pub async fn get_note(
 pub async fn trigger_reindex(
     state: tauri::State<'_, AppMutex>,
     app: tauri::AppHandle,
-) -> Result<(), String> {
+) -> Result<(), AppError> {
     let db_path = state
         .lock()
         .await
         .db_path
         .clone()
-        .ok_or("db_not_configured")?;
+        .ok_or(AppError::DbNotConfigured)?;
     tauri::async_runtime::spawn(async move {
         run_delta_update(app, db_path).await;
     });
     Ok(())
 }
 
+/// Fetch a past version of a note (title and body as of that revision).
+/// Called when the user selects a revision search result.
+#[tauri::command]
+pub async fn get_note_revision(
+    revision_id: String,
+    state: tauri::State<'_, AppMutex>,
+) -> Result<Note, AppError> {
+    let (db_path, note_id) = {
+        let s = state.lock().await;
+        let db_path = s.db_path.clone().ok_or(AppError::DbNotConfigured)?;
+        let note_id = s
+            .revision_cache
+            .get(&revision_id)
+            .map(|r| r.note_id.clone())
+            .ok_or(AppError::RevisionNotFound)?;
+        (db_path, note_id)
+    };
+    let conn = crate::db::open_joplin_db(&db_path).map_err(AppError::database)?;
+    let schema = crate::db::read_schema(&conn).map_err(AppError::database)?;
+    let rows = crate::db::get_revision_rows(&conn, &schema, Some(&note_id))
+        .map_err(AppError::database)?;
+    crate::revisions::reconstruct_all(rows)
+        .into_iter()
+        .find(|r| r.id == revision_id)
+        .map(|r| Note {
+            id: r.note_id,
+            title: r.title,
+            body: r.body,
+            updated_time: r.revision_time,
+            ..Default::default()
+        })
+        .ok_or(AppError::RevisionNotFound)
+}
+
+/// Notes that link to `note_id` with a `:/id` link, sorted by title.
+#[tauri::command]
+pub async fn get_backlinks(
+    note_id: String,
+    state: tauri::State<'_, AppMutex>,
+) -> Result<Vec<NoteMetadata>, AppError> {
+    let s = state.lock().await;
+    Ok(linked_notes(&s, s.link_graph.backlinks(&note_id)))
+}
+
+/// Notes that `note_id` links to, sorted by title. Links to attachments
+/// and to notes that aren't indexed are left out.
+#[tauri::command]
+pub async fn get_outgoing_links(
+    note_id: String,
+    state: tauri::State<'_, AppMutex>,
+) -> Result<Vec<NoteMetadata>, AppError> {
+    let s = state.lock().await;
+    Ok(linked_notes(&s, s.link_graph.outgoing(&note_id)))
+}
+
+/// Group the whole vault into topics by clustering the note embeddings.
+/// `cluster_count` overrides the automatic number of clusters.
+#[tauri::command]
+pub async fn get_topic_map(
+    cluster_count: Option<usize>,
+    state: tauri::State<'_, AppMutex>,
+) -> Result<Vec<TopicCluster>, AppError> {
+    let (db_path, index_arc, notes) = {
+        let s = state.lock().await;
+        let db_path = s.db_path.clone().ok_or(AppError::DbNotConfigured)?;
+        let index = s.search_index.clone().ok_or(AppError::IndexNotReady)?;
+        let notes: HashMap<String, NoteMetadata> = s
+            .note_cache
+            .iter()
+            .filter(|(id, _)| !s.deleted_note_ids.contains(*id))
+            .map(|(id, meta)| (id.clone(), meta.clone()))
+            .collect();
+        (db_path, index, notes)
+    };
+    let vectors: Vec<(String, Vec<f32>)> = {
+        let index = index_arc.read().await;
+        index
+            .vectors()
+            .iter()
+            .filter(|(id, _)| notes.contains_key(*id))
+            .map(|(id, v)| (id.clone(), v.clone()))
+            .collect()
+    };
+
+    tokio::task::spawn_blocking(move || {
+        // Notebook names are only used for labelling; carry on without them.
+        let notebooks = crate::db::open_joplin_db(&db_path)
+            .and_then(|conn| crate::db::get_notebook_paths(&conn))
+            .unwrap_or_default();
+        crate::topics::build_topic_map(vectors, &notes, &notebooks, cluster_count)
+    })
+    .await
+    .map_err(AppError::internal)
+}
+
+/// Report groups of near-duplicate notes (copies, repeated web clips) so they
+/// can be cleaned up in Joplin. See `DuplicateOptions` for the thresholds.
+#[tauri::command]
+pub async fn find_duplicates(
+    options: Option<DuplicateOptions>,
+    state: tauri::State<'_, AppMutex>,
+) -> Result<Vec<DuplicateGroup>, AppError> {
+    let options = options.unwrap_or_default();
+    let (db_path, scope, index_arc, notes) = {
+        let s = state.lock().await;
+        let db_path = s.db_path.clone().ok_or(AppError::DbNotConfigured)?;
+        let index = s.search_index.clone().ok_or(AppError::IndexNotReady)?;
+        let notes: HashMap<String, NoteMetadata> = s
+            .note_cache
+            .iter()
+            .filter(|(id, _)| !s.deleted_note_ids.contains(*id))
+            .map(|(id, meta)| (id.clone(), meta.clone()))
+            .collect();
+        (db_path, crate::db::NoteScope::from(&s.settings), index, notes)
+    };
+    // One neighbour search per note: run it off the async runtime, holding
+    // only a read lock so regular searches carry on meanwhile.
+    let index = index_arc.read_owned().await;
+
+    tokio::task::spawn_blocking(move || {
+        let mut pairs = crate::duplicates::similar_pairs(&index, &notes, options.threshold);
+        drop(index);
+
+        let conn = crate::db::open_joplin_db(&db_path).map_err(AppError::database)?;
+        if options.text_check && !pairs.is_empty() {
+            let schema = crate::db::read_schema(&conn).map_err(AppError::database)?;
+            let bodies =
+                crate::db::get_all_notes(&conn, &schema, scope).map_err(AppError::database)?;
+            crate::duplicates::apply_text_check(&mut pairs, &bodies, options.min_text_similarity);
+        }
+        let notebooks = crate::db::get_notebook_paths(&conn).unwrap_or_default();
+        Ok(crate::duplicates::group_pairs(pairs, &notes, &notebooks))
+    })
+    .await
+    .map_err(AppError::internal)?
+}
+
+/// Propose tags and a notebook from the nearest tagged neighbours, for one
+/// note or (without `note_id`) for every untagged note. Read-only: the
+/// suggestions are for display or export, nothing is written to Joplin.
+#[tauri::command]
+pub async fn suggest_tags_and_notebook(
+    note_id: Option<String>,
+    state: tauri::State<'_, AppMutex>,
+) -> Result<Vec<NoteSuggestion>, AppError> {
+    let (db_path, index_arc, notes) = {
+        let s = state.lock().await;
+        let db_path = s.db_path.clone().ok_or(AppError::DbNotConfigured)?;
+        let index = s.search_index.clone().ok_or(AppError::IndexNotReady)?;
+        let notes: HashMap<String, NoteMetadata> = s
+            .note_cache
+            .iter()
+            .filter(|(id, _)| !s.deleted_note_ids.contains(*id))
+            .map(|(id, meta)| (id.clone(), meta.clone()))
+            .collect();
+        (db_path, index, notes)
+    };
+    let index = index_arc.read_owned().await;
+
+    tokio::task::spawn_blocking(move || {
+        let conn = crate::db::open_joplin_db(&db_path).map_err(AppError::database)?;
+        let note_tags = crate::db::get_note_tags(&conn).map_err(AppError::database)?;
+        let notebooks = crate::db::get_notebook_paths(&conn).unwrap_or_default();
+        let suggest = |id: &str| {
+            crate::suggest::suggest_for_note(&index, id, &notes, &note_tags, &notebooks)
+        };
+
+        if let Some(id) = note_id {
+            return suggest(&id).map(|s| vec![s]).ok_or(AppError::NoteNotFound);
+        }
+        let mut suggestions: Vec<NoteSuggestion> = notes
+            .keys()
+            .filter(|id| !note_tags.contains_key(*id))
+            .filter_map(|id| suggest(id))
+            // Keep notes with something to act on: a tag, or a better notebook.
+            .filter(|s| {
+                !s.tags.is_empty()
+                    || s.notebooks
+                        .first()
+                        .is_some_and(|nb| nb.notebook_id != notes[&s.note_id].parent_id)
+            })
+            .collect();
+        let best = |s: &NoteSuggestion| s.tags.first().map_or(0.0, |t| t.confidence);
+        suggestions
+            .sort_by(|a, b| best(b).partial_cmp(&best(a)).unwrap_or(std::cmp::Ordering::Equal));
+        Ok(suggestions)
+    })
+    .await
+    .map_err(AppError::internal)?
+}
+
+/// Current user settings.
+#[tauri::command]
+pub async fn get_settings(state: tauri::State<'_, AppMutex>) -> Result<Settings, AppError> {
+    Ok(state.lock().await.settings.clone())
+}
+
+/// Replace the user settings, persist them, and apply the ones that take effect immediately.
+#[tauri::command]
+pub async fn update_settings(
+    settings: Settings,
+    state: tauri::State<'_, AppMutex>,
+    app: tauri::AppHandle,
+) -> Result<(), AppError> {
+    let mut settings = settings;
+    {
+        let s = state.lock().await;
+        // The database is only changed through `set_joplin_db_path`.
+        settings.db_path = s.settings.db_path.clone();
+        if settings.resources != s.settings.resources
+            && !crate::embeddings::limits_supported(&settings.resources)
+        {
+            return Err(AppError::LimitsUnsupported);
+        }
+    }
+    crate::settings::save(&settings, &settings_file_path(&app)).map_err(AppError::storage)?;
+
+    let (revisions_enabled, scope_changed, cleared_history, watcher_changed, workers_changed) = {
+        let mut s = state.lock().await;
+        let was_enabled = s.settings.index_revisions;
+        let workers_changed = pipeline_settings_changed(&s.settings, &settings);
+        let watcher_changed = s.settings.watcher_debounce_ms != settings.watcher_debounce_ms
+            || s.settings.watcher_polling != settings.watcher_polling;
+        // Turning history off also forgets what was already recorded.
+        let cleared_history = (settings.disable_history && !s.history.recent.is_empty())
+            .then(|| {
+                s.history.recent.clear();
+                s.history.clone()
+            });
+        let old_scope = crate::db::NoteScope::from(&s.settings);
+        s.settings = settings;
+        if let Some(pipeline) = &s.embedding_pipeline {
+            pipeline.set_background_slice(s.settings.resources.inference_slice);
+        }
+        let scope_changed = old_scope != crate::db::NoteScope::from(&s.settings);
+        if !s.settings.index_revisions {
+            // Free the in-memory revision index. The files on disk are kept so
+            // turning the option back on doesn't re-embed everything.
+            s.revision_index = None;
+            s.revision_cache.clear();
+            s.revision_fingerprint = (0, 0);
+        }
+        let revisions_enabled = (!was_enabled && s.settings.index_revisions)
+            .then(|| s.db_path.clone())
+            .flatten();
+        (
+            revisions_enabled,
+            scope_changed && s.db_path.is_some(),
+            cleared_history,
+            watcher_changed,
+            workers_changed,
+        )
+    };
+
+    if watcher_changed {
+        crate::watcher::reconfigure(&app).await;
+    }
+
+    if workers_changed {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            restart_pipeline(app).await;
+        });
+    }
+
+    if let Some(history) = cleared_history {
+        crate::history::save(&history, &history_file_path(&app)).map_err(AppError::storage)?;
+    }
+
+    if scope_changed {
+        // The saved index was built for the old set of notes; re-embed from scratch.
+        // The current index keeps serving searches until the rebuild finishes.
+        crate::index::delete_saved(&index_file_path(&app));
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            run_full_indexing(app).await;
+        });
+    }
+
+    if let Some(db_path) = revisions_enabled {
+        tauri::async_runtime::spawn(async move {
+            run_revision_indexing(app, db_path).await;
+        });
+    }
+    Ok(())
+}
+
 // ─── Internal helpers ──────────────────────────────────────────────────────────
 
 /// Called once on startup: auto-detect DB, load or build index, start watcher.
 pub async fn startup_init(app: tauri::AppHandle) {
-    let db_path = crate::db::detect_joplin_db_path();
+    {
+        let settings = crate::settings::load(&settings_file_path(&app));
+        let usage = crate::usage::load(&usage_file_path(&app));
+        let history = crate::history::load(&history_file_path(&app));
+        let state = app.state::<AppMutex>();
+        let mut s = state.lock().await;
+        s.settings = settings;
+        s.usage = usage;
+        s.history = history;
+    }
+    crate::maintenance::start_scheduler(app.clone());
+
+    // A database the user chose before wins while it still exists. Otherwise
+    // only auto-select when detection is unambiguous: with several profiles
+    // or installs, the first-launch screen lets the user pick one.
+    let saved = {
+        let state = app.state::<AppMutex>();
+        let s = state.lock().await;
+        s.settings.db_path.clone().filter(|p| std::path::Path::new(p).exists())
+    };
+    let db_path = saved.or_else(|| {
+        let mut candidates = crate::db::detect_joplin_db_candidates();
+        if candidates.len() == 1 { candidates.pop().map(|c| c.path) } else { None }
+    });
 
-    if let Some(path) = db_path {
-        let path_str = path.to_string_lossy().to_string();
+    if let Some(path_str) = db_path {
         {
             let state = app.state::<AppMutex>();
             let mut s = state.lock().await;
@@ -148,70 +873,180 @@ pub async fn startup_init(app: tauri::AppHandle) {
         run_delta_update(app.clone(), path_str).await;
         crate::watcher::start_watcher(app).await;
     }
-    // If DB not found: index_status remains is_ready=false, db_path=None.
-    // The frontend first-launch screen will prompt the user to locate it.
+    // If DB not found (or ambiguous): index_status remains is_ready=false, db_path=None.
+    // The frontend first-launch screen will prompt the user to choose or locate it.
 }
 
 /// Build (or rebuild) the full HNSW index from the Joplin SQLite database.
 /// Emits "index-status" events so the frontend can show progress.
 pub async fn run_full_indexing(app: tauri::AppHandle) {
+    // Failures are reported through the status and the error log.
+    let _ = full_indexing(app, true).await;
+}
+
+/// Re-embed every note into a new index, ignoring the saved one. The saved
+/// index and manifest are only replaced once the new ones are saved, and the
+/// current index keeps serving searches until then.
+pub async fn rebuild_index(app: tauri::AppHandle) -> Result<(), AppError> {
+    full_indexing(app, false).await
+}
+
+async fn full_indexing(app: tauri::AppHandle, load_saved: bool) -> Result<(), AppError> {
     // 0. Guard against concurrent rebuilds
     {
         let state = app.state::<AppMutex>();
         let mut s = state.lock().await;
         if s.is_indexing {
-            return;
+            return Err(AppError::IndexBusy);
         }
         s.is_indexing = true;
+        s.cancel_indexing = false;
+        s.index_status.error = None;
     }
 
-    run_full_indexing_inner(app.clone()).await;
+    let result = run_full_indexing_inner(app.clone(), load_saved).await;
 
     let state = app.state::<AppMutex>();
-    state.lock().await.is_indexing = false;
+    let mut s = state.lock().await;
+    s.is_indexing = false;
+    s.cancel_indexing = false;
+    result
+}
+
+/// Change how much of the machine inference may use. Thread and priority
+/// changes restart the inference workers (searches keep using the old ones
+/// until the new ones are loaded); the batch and slice sizes apply from the
+/// next batch.
+/// Fails with `AppError::LimitsUnsupported` for thread or priority limits
+/// outside Linux.
+#[tauri::command]
+pub async fn set_resource_limits(
+    limits: ResourceLimits,
+    state: tauri::State<'_, AppMutex>,
+    app: tauri::AppHandle,
+) -> Result<(), AppError> {
+    if !crate::embeddings::limits_supported(&limits) {
+        return Err(AppError::LimitsUnsupported);
+    }
+    let (settings, restart) = {
+        let mut s = state.lock().await;
+        let mut settings = s.settings.clone();
+        settings.resources = limits;
+        let restart = pipeline_settings_changed(&s.settings, &settings);
+        if let Some(pipeline) = &s.embedding_pipeline {
+            pipeline.set_background_slice(settings.resources.inference_slice);
+        }
+        s.settings = settings.clone();
+        (settings, restart)
+    };
+    crate::settings::save(&settings, &settings_file_path(&app)).map_err(AppError::storage)?;
+    if restart {
+        tauri::async_runtime::spawn(async move {
+            restart_pipeline(app).await;
+        });
+    }
+    Ok(())
+}
+
+/// Stop the running full build after its current batch. The notes embedded so
+/// far are checkpointed and the next build (`resume_indexing` or the next
+/// launch) continues from them. A previously loaded index keeps serving.
+#[tauri::command]
+pub async fn cancel_indexing(state: tauri::State<'_, AppMutex>) -> Result<(), AppError> {
+    let mut s = state.lock().await;
+    if !s.is_indexing {
+        return Err(AppError::NotIndexing);
+    }
+    s.cancel_indexing = true;
+    Ok(())
+}
+
+/// Start a full build again after `cancel_indexing`, continuing from its
+/// checkpoint. Does nothing while a build is running.
+#[tauri::command]
+pub async fn resume_indexing(
+    state: tauri::State<'_, AppMutex>,
+    app: tauri::AppHandle,
+) -> Result<(), AppError> {
+    if state.lock().await.db_path.is_none() {
+        return Err(AppError::DbNotConfigured);
+    }
+    tauri::async_runtime::spawn(async move {
+        run_full_indexing(app).await;
+    });
+    Ok(())
 }
 
-async fn run_full_indexing_inner(app: tauri::AppHandle) {
-    // 1. Grab db_path
-    let db_path = {
+async fn run_full_indexing_inner(app: tauri::AppHandle, load_saved: bool) -> Result<(), AppError> {
+    // 1. Grab db_path, which note categories to include and the batch size
+    let (db_path, scope, batch_size) = {
         let state = app.state::<AppMutex>();
         let s = state.lock().await;
         match s.db_path.clone() {
-            Some(p) => p,
-            None => return,
+            Some(p) => (
+                p,
+                crate::db::NoteScope::from(&s.settings),
+                s.settings.resources.batch_size.max(1),
+            ),
+            None => return Err(AppError::DbNotConfigured),
         }
     };
 
+    // 1b. Validate the schema up front so an incompatible database reports
+    // the exact reason instead of failing somewhere inside a query.
+    let schema = match crate::db::open_joplin_db(&db_path)
+        .and_then(|conn| crate::db::read_schema(&conn))
+    {
+        Ok(s) => s,
+        Err(e) => return Err(fail_indexing(&app, AppError::database(e)).await),
+    };
+
     // 2. Try loading a saved index (avoids re-embedding on every launch)
     let index_path = index_file_path(&app);
-    if index_path.exists() {
-        if let Ok(loaded) = crate::index::SearchIndex::load(&index_path) {
+    if load_saved && index_path.exists() {
+        {
+            let state = app.state::<AppMutex>();
+            let mut s = state.lock().await;
+            s.index_status.enter(IndexPhase::LoadingIndex);
+            let _ = app.emit("index-status", &s.index_status);
+        }
+        if let Ok(mut loaded) = crate::index::SearchIndex::load(&index_path) {
             if let Ok(conn) = crate::db::open_joplin_db(&db_path) {
-                if let Ok(notes) = crate::db::get_all_notes(&conn) {
+                if let Ok(notes) = crate::db::get_all_notes(&conn, &schema, scope) {
                     let total = notes.len();
-                    let max_ts = notes.iter().map(|n| n.updated_time).max().unwrap_or(0);
+                    // Drop notes deleted while the app was closed, including any
+                    // the manifest doesn't know about (older or interrupted saves).
+                    let live: HashSet<&str> = notes.iter().map(|n| n.id.as_str()).collect();
+                    let gone: Vec<String> = loaded
+                        .vectors()
+                        .keys()
+                        .filter(|id| !live.contains(id.as_str()))
+                        .cloned()
+                        .collect();
+                    for id in &gone {
+                        loaded.remove(id);
+                    }
+                    // An index saved before manifests (or their schema version)
+                    // were recorded is assumed current.
+                    let mut manifest = crate::manifest::load(&manifest_file_path(&app))
+                        .unwrap_or_else(|| {
+                            crate::manifest::Manifest::from_notes(schema.version, &notes)
+                        });
+                    manifest.adopt_schema(schema.version);
                     let note_cache: HashMap<String, NoteMetadata> =
-                        notes.into_iter().map(|n| (n.id.clone(), NoteMetadata {
-                            id: n.id,
-                            title: n.title,
-                            updated_time: n.updated_time,
-                        })).collect();
+                        notes.iter().map(|n| (n.id.clone(), n.metadata())).collect();
+                    let link_graph = LinkGraph::from_notes(&notes);
 
                     let state = app.state::<AppMutex>();
                     let mut s = state.lock().await;
                     s.search_index = Some(Arc::new(tokio::sync::RwLock::new(loaded)));
                     s.note_cache = note_cache;
-                    s.last_scan_timestamp = max_ts;
+                    s.link_graph = link_graph;
+                    s.manifest = manifest;
                     s.deleted_note_ids.clear();
-                    s.last_full_rebuild_ts = std::time::Instant::now();
-                    s.index_status = IndexStatus {
-                        total_notes: total,
-                        indexed_notes: total,
-                        is_ready: false, // not yet — need the model for queries
-                        is_downloading_model: true,
-                        download_progress: 1.0,
-                        error: None,
-                    };
+                    s.index_status.total_notes = total;
+                    s.index_status.indexed_notes = total;
+                    s.index_status.error = None;
                     let _ = app.emit("index-status", &s.index_status);
                     drop(s);
 
@@ -220,46 +1055,40 @@ async fn run_full_indexing_inner(app: tauri::AppHandle) {
 
                     let state = app.state::<AppMutex>();
                     let mut s = state.lock().await;
-                    s.index_status.is_downloading_model = false;
                     if s.embedding_pipeline.is_some() {
                         s.index_status.is_ready = true;
+                        s.index_status.enter(IndexPhase::Ready);
                     }
                     let _ = app.emit("index-status", &s.index_status);
-                    return;
+                    return Ok(());
                 }
             }
         }
     }
 
-    // 3. Full build: init model
-    {
-        let state = app.state::<AppMutex>();
-        let mut s = state.lock().await;
-        s.index_status.is_downloading_model = true;
-        let _ = app.emit("index-status", &s.index_status);
-    }
-
+    // 3. Full build: init model. Without it there is nothing to build; the
+    // load failure is already reported.
     ensure_pipeline_loaded(app.clone()).await;
 
     {
         let state = app.state::<AppMutex>();
         let mut s = state.lock().await;
-        s.index_status.is_downloading_model = false;
-        let _ = app.emit("index-status", &s.index_status);
+        if s.embedding_pipeline.is_none() {
+            return Err(AppError::ModelNotLoaded);
+        }
+        if s.cancel_indexing {
+            s.index_status.fail(AppError::IndexingCancelled);
+            let _ = app.emit("index-status", &s.index_status);
+            return Err(AppError::IndexingCancelled);
+        }
     }
 
     // 4. Read all notes
     let notes = match crate::db::open_joplin_db(&db_path)
-        .and_then(|conn| crate::db::get_all_notes(&conn))
+        .and_then(|conn| crate::db::get_all_notes(&conn, &schema, scope))
     {
         Ok(n) => n,
-        Err(e) => {
-            let state = app.state::<AppMutex>();
-            let mut s = state.lock().await;
-            s.index_status.error = Some(format!("Failed to read database: {e}"));
-            let _ = app.emit("index-status", &s.index_status);
-            return;
-        }
+        Err(e) => return Err(fail_indexing(&app, AppError::database(e)).await),
     };
 
     let total = notes.len();
@@ -275,24 +1104,59 @@ async fn run_full_indexing_inner(app: tauri::AppHandle) {
     let mut search_index = match crate::index::SearchIndex::new((total * 2).max(2000)) {
         Ok(i) => i,
         Err(e) => {
-            let state = app.state::<AppMutex>();
-            let mut s = state.lock().await;
-            s.index_status.error = Some(format!("Failed to create index: {e}"));
-            let _ = app.emit("index-status", &s.index_status);
-            return;
+            let error = AppError::Internal(format!("Failed to create index: {e}"));
+            return Err(fail_indexing(&app, error).await);
         }
     };
 
-    let mut note_cache: HashMap<String, NoteMetadata> = HashMap::new();
-    let mut max_ts: i64 = 0;
-    let mut indexed = 0;
-    const BATCH: usize = 64;
+    let note_cache: HashMap<String, NoteMetadata> =
+        notes.iter().map(|n| (n.id.clone(), n.metadata())).collect();
+    let mut manifest = crate::manifest::Manifest::new(schema.version);
+
+    // 5a. Resume from the checkpoint of a cancelled or interrupted build:
+    // reuse the vectors of notes whose text hasn't changed since.
+    if let Some((partial, partial_manifest)) = load_checkpoint(&app) {
+        let resumed: Vec<&Note> = notes
+            .iter()
+            .filter(|n| partial_manifest.same_content(n) && partial.vector(&n.id).is_some())
+            .collect();
+        let entries: Vec<(String, Vec<f32>)> = resumed
+            .iter()
+            .filter_map(|n| Some((n.id.clone(), partial.vector(&n.id)?.to_vec())))
+            .collect();
+        if search_index.add_batch(entries).is_ok() {
+            for note in &resumed {
+                manifest.insert(note);
+            }
+            tracing::info!(
+                "Resuming full index build: {} notes from checkpoint",
+                resumed.len()
+            );
+        }
+    }
 
-    for chunk in notes.chunks(BATCH) {
+    let pending: Vec<&Note> = notes
+        .iter()
+        .filter(|n| !manifest.notes.contains_key(&n.id))
+        .collect();
+    let resumed = total - pending.len();
+    let mut indexed = resumed;
+    let started = std::time::Instant::now();
+    let mut last_checkpoint = started;
+    {
+        let state = app.state::<AppMutex>();
+        let mut s = state.lock().await;
+        s.index_status.enter(IndexPhase::Embedding);
+        s.index_status.indexed_notes = indexed;
+        s.index_status.progress = Some(indexed as f32 / total.max(1) as f32);
+        let _ = app.emit("index-status", &s.index_status);
+    }
+
+    for chunk in pending.chunks(batch_size) {
         // Clone the Arc outside the lock so inference happens lock-free
         let texts_owned: Vec<String> = chunk
             .iter()
-            .map(|n| format!("{}\n\n{}", n.title, n.body))
+            .map(|n| crate::preprocess::embedding_text(&n.title, &n.body, n.markup_language))
             .collect();
         let texts: Vec<&str> = texts_owned.iter().map(|s| s.as_str()).collect();
         let pipeline_arc = {
@@ -300,56 +1164,152 @@ async fn run_full_indexing_inner(app: tauri::AppHandle) {
             let guard = state.lock().await;
             guard.embedding_pipeline.clone()
         };
-        let embeddings = pipeline_arc.and_then(|p| p.embed_batch(&texts).ok());
+        let embeddings = match pipeline_arc {
+            Some(p) => p.embed_batch(&texts, Priority::Background).await,
+            None => Err(anyhow::anyhow!("embedding model unloaded")),
+        };
 
-        if let Some(embeddings) = embeddings {
+        // Notes left out of the manifest are picked up by the next delta pass.
+        let added = embeddings.map_err(AppError::inference).and_then(|embeddings| {
             let entries: Vec<(String, Vec<f32>)> = chunk
                 .iter()
                 .zip(embeddings)
                 .filter(|(note, _)| is_valid_joplin_id(&note.id))
                 .map(|(note, emb)| (note.id.clone(), emb))
                 .collect();
-            let _ = search_index.add_batch(entries);
+            search_index.add_batch(entries).map_err(AppError::internal)
+        });
+        match added {
+            Ok(()) => {
+                // Notes with malformed IDs were never added, so leave them out.
+                for note in chunk.iter().filter(|n| is_valid_joplin_id(&n.id)) {
+                    manifest.insert(note);
+                }
+            }
+            Err(e) => crate::error::record(&app, "full_indexing", e).await,
         }
 
-        for note in chunk {
-            max_ts = max_ts.max(note.updated_time);
-            note_cache.insert(note.id.clone(), NoteMetadata {
-                id: note.id.clone(),
-                title: note.title.clone(),
-                updated_time: note.updated_time,
-            });
+        indexed += chunk.len();
+        let cancelled = {
+            let state = app.state::<AppMutex>();
+            let mut s = state.lock().await;
+            s.index_status.indexed_notes = indexed;
+            s.index_status.progress = Some(indexed as f32 / total.max(1) as f32);
+            // Throughput of this run only; notes from a checkpoint came for free.
+            let rate = (indexed - resumed) as f32 / started.elapsed().as_secs_f32().max(0.001);
+            s.index_status.notes_per_sec = Some(rate);
+            s.index_status.eta_secs = Some(((total - indexed) as f32 / rate.max(0.001)) as u64);
+            let _ = app.emit("index-status", &s.index_status);
+            s.cancel_indexing
+        };
+
+        if cancelled {
+            save_checkpoint(&app, &search_index, &manifest);
+            tracing::info!("Full index build cancelled at {indexed}/{total} notes");
+            let state = app.state::<AppMutex>();
+            let mut s = state.lock().await;
+            s.index_status.fail(AppError::IndexingCancelled);
+            let _ = app.emit("index-status", &s.index_status);
+            return Err(AppError::IndexingCancelled);
+        }
+        if last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+            save_checkpoint(&app, &search_index, &manifest);
+            last_checkpoint = std::time::Instant::now();
         }
+    }
 
-        indexed += chunk.len();
+    // 6. Persist index to disk, then the manifest describing it. The
+    // checkpoint is only dropped once both are saved.
+    {
         let state = app.state::<AppMutex>();
         let mut s = state.lock().await;
-        s.index_status.indexed_notes = indexed;
-        s.index_status.download_progress = indexed as f32 / total.max(1) as f32;
+        s.index_status.enter(IndexPhase::Saving);
         let _ = app.emit("index-status", &s.index_status);
     }
-
-    // 6. Persist index to disk
-    let _ = search_index.save(&index_path);
+    let saved = search_index
+        .save(&index_path)
+        .and_then(|_| crate::manifest::save(&manifest, &manifest_file_path(&app)));
+    let saved = match saved {
+        Ok(()) => {
+            crate::index::delete_saved(&checkpoint_index_file_path(&app));
+            let _ = std::fs::remove_file(checkpoint_manifest_file_path(&app));
+            Ok(())
+        }
+        // The built index still serves this session; the checkpoint is kept.
+        Err(e) => {
+            let error = AppError::Storage(format!("Failed to save index: {e}"));
+            crate::error::record(&app, "full_indexing", error.clone()).await;
+            Err(error)
+        }
+    };
+    let link_graph = LinkGraph::from_notes(&notes);
 
     // 7. Update state and mark ready (only if pipeline loaded successfully)
     let state = app.state::<AppMutex>();
     let mut s = state.lock().await;
     s.search_index = Some(Arc::new(tokio::sync::RwLock::new(search_index)));
     s.note_cache = note_cache;
-    s.last_scan_timestamp = max_ts;
+    s.link_graph = link_graph;
+    s.manifest = manifest;
     s.deleted_note_ids.clear();
-    s.last_full_rebuild_ts = std::time::Instant::now();
-    if s.embedding_pipeline.is_some() {
+    let result = if s.embedding_pipeline.is_some() {
         s.index_status.is_ready = true;
-    }
-    s.index_status.download_progress = 1.0;
-    s.index_status.error = None;
+        s.index_status.enter(IndexPhase::Ready);
+        s.index_status.error = None;
+        saved
+    } else {
+        s.index_status.fail(AppError::ModelNotLoaded);
+        Err(AppError::ModelNotLoaded)
+    };
     let _ = app.emit("index-status", &s.index_status);
+    result
+}
+
+/// Report a failed full build in the index status and the error log.
+async fn fail_indexing(app: &tauri::AppHandle, error: AppError) -> AppError {
+    {
+        let state = app.state::<AppMutex>();
+        let mut s = state.lock().await;
+        s.index_status.fail(error.clone());
+        let _ = app.emit("index-status", &s.index_status);
+    }
+    crate::error::record(app, "full_indexing", error.clone()).await;
+    error
+}
+
+/// How often a running full build saves a checkpoint to resume from.
+const CHECKPOINT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Save the partially built index and the manifest of the notes in it.
+/// Best-effort: a failed checkpoint only costs re-embedding on resume.
+fn save_checkpoint(
+    app: &tauri::AppHandle,
+    index: &crate::index::SearchIndex,
+    manifest: &crate::manifest::Manifest,
+) {
+    let saved = index
+        .save(&checkpoint_index_file_path(app))
+        .and_then(|_| crate::manifest::save(manifest, &checkpoint_manifest_file_path(app)));
+    if let Err(e) = saved {
+        tracing::warn!("Failed to save indexing checkpoint: {e}");
+    }
+}
+
+/// The checkpoint left by an unfinished full build, if it was embedded with
+/// the current model.
+fn load_checkpoint(
+    app: &tauri::AppHandle,
+) -> Option<(crate::index::SearchIndex, crate::manifest::Manifest)> {
+    let manifest = crate::manifest::load(&checkpoint_manifest_file_path(app))?;
+    if manifest.model != crate::embeddings::MODEL_ID {
+        return None;
+    }
+    let index = crate::index::SearchIndex::load(&checkpoint_index_file_path(app)).ok()?;
+    Some((index, manifest))
 }
 
 /// Run a delta update: immediately handle new, edited, and deleted notes.
-/// Schedules a background full rebuild if 5 minutes have passed since the last one.
+/// Index upkeep (compaction, rebuilds) is left to `maintenance`.
 pub async fn run_delta_update(app: tauri::AppHandle, db_path: String) {
     // Guard: prevent two overlapping delta passes from double-inserting embeddings.
     {
@@ -359,66 +1319,125 @@ pub async fn run_delta_update(app: tauri::AppHandle, db_path: String) {
             return;
         }
         s.is_delta_updating = true;
+        s.last_activity = std::time::Instant::now();
     }
 
-    run_delta_update_inner(app.clone(), db_path).await;
+    let result = run_delta_update_inner(app.clone(), db_path.clone()).await;
 
-    let state = app.state::<AppMutex>();
-    state.lock().await.is_delta_updating = false;
+    {
+        let state = app.state::<AppMutex>();
+        state.lock().await.is_delta_updating = false;
+    }
+    if let Err(e) = result {
+        crate::error::record(&app, "delta_update", e).await;
+    }
+
+    // Joplin writes revisions on its own schedule, independent of note edits,
+    // so check for new ones on every pass (no-op unless enabled in settings).
+    tauri::async_runtime::spawn(async move {
+        run_revision_indexing(app, db_path).await;
+    });
 }
 
-async fn run_delta_update_inner(app: tauri::AppHandle, db_path: String) {
-    // 1. Grab last timestamps
-    let (last_ts, last_rebuild_ts) = {
+async fn run_delta_update_inner(app: tauri::AppHandle, db_path: String) -> Result<(), AppError> {
+    // 1. Grab the manifest of what the index holds and which note categories to include
+    let (manifest, scope) = {
         let state = app.state::<AppMutex>();
         let s = state.lock().await;
-        (s.last_scan_timestamp, s.last_full_rebuild_ts)
+        (s.manifest.clone(), crate::db::NoteScope::from(&s.settings))
     };
 
-    // 2. Cheap check: anything changed at all?
-    let conn = match crate::db::open_joplin_db(&db_path) {
-        Ok(c) => c,
-        Err(_) => return,
-    };
-    match crate::db::has_notes_since(&conn, last_ts) {
-        Ok(false) => return,
-        Err(_) => return,
-        Ok(true) => {}
+    // 2. Cheap check: diff the current note versions (no bodies) against the
+    // manifest. A set difference rather than an updated_time watermark, so
+    // notes synced with skewed clocks or restored with old timestamps count too.
+    let conn = crate::db::open_joplin_db(&db_path).map_err(AppError::database)?;
+    let schema = crate::db::read_schema(&conn).map_err(AppError::database)?;
+    let versions =
+        crate::db::get_note_versions(&conn, &schema, scope).map_err(AppError::database)?;
+    let diff = manifest.diff(&versions);
+    if diff.is_empty() {
+        return Ok(());
     }
 
-    // 3. Handle deleted notes — update tombstone set + remove from cache
-    let deleted_ids = crate::db::get_deleted_note_ids_since(&conn, last_ts)
-        .unwrap_or_default();
-    if !deleted_ids.is_empty() {
+    // 3. Handle notes that left the scope — soft- or hard-deleted, or trashed
+    // (alone or with their notebook) when the trash isn't included. Remove
+    // them from the index and cache; the tombstone set covers searches that
+    // already hold the index.
+    let mut index_changed = !diff.removed.is_empty();
+    if !diff.removed.is_empty() {
+        let index_arc = {
+            let state = app.state::<AppMutex>();
+            let mut s = state.lock().await;
+            for id in &diff.removed {
+                s.deleted_note_ids.insert(id.clone());
+                s.note_cache.remove(id);
+                s.link_graph.remove_note(id);
+                s.manifest.notes.remove(id);
+            }
+            s.search_index.clone()
+        };
+        if let Some(arc) = index_arc {
+            let mut index = arc.write().await;
+            for id in &diff.removed {
+                index.remove(id);
+            }
+        }
+    }
+
+    // 4. Fetch new and changed notes. Notes whose text is unchanged (moved,
+    // trashed or restored, touched by sync) only get fresh metadata.
+    let ids: Vec<String> = diff.added.into_iter().chain(diff.changed).collect();
+    let (unchanged, changed_notes): (Vec<Note>, Vec<Note>) =
+        crate::db::get_notes_by_ids(&conn, &schema, scope, &ids)
+            .map_err(AppError::database)?
+            .into_iter()
+            .partition(|n| manifest.same_content(n));
+    if !unchanged.is_empty() {
         let state = app.state::<AppMutex>();
         let mut s = state.lock().await;
-        for id in &deleted_ids {
-            s.deleted_note_ids.insert(id.clone());
-            s.note_cache.remove(id);
+        for note in &unchanged {
+            s.note_cache.insert(note.id.clone(), note.metadata());
+            s.manifest.insert(note);
         }
     }
 
-    // 4. Embed and insert new/edited notes into the live index
-    let changed_notes = crate::db::get_notes_since(&conn, last_ts).unwrap_or_default();
-
+    // 5. Embed and insert new/edited notes into the live index, a batch at a time
     if !changed_notes.is_empty() {
         // Clone pipeline Arc outside the lock
-        let pipeline_arc = {
+        let (pipeline_arc, batch_size) = {
             let state = app.state::<AppMutex>();
             let s = state.lock().await;
-            s.embedding_pipeline.clone()
+            (
+                s.embedding_pipeline.clone(),
+                s.settings.resources.batch_size.max(1),
+            )
         };
 
         if let Some(pipeline) = pipeline_arc {
-            let texts_owned: Vec<String> = changed_notes
-                .iter()
-                .map(|n| format!("{}\n\n{}", n.title, n.body))
-                .collect();
-            let texts: Vec<&str> = texts_owned.iter().map(|s| s.as_str()).collect();
+            // Vectors of edited notes are kept from before the insert so
+            // saved-search alerts can tell new matches from old ones.
+            let mut previous: HashMap<String, Vec<f32>> = HashMap::new();
+            let mut entries: Vec<(String, Vec<f32>)> = Vec::new();
 
-            // Embed outside both locks
-            if let Ok(embeddings) = pipeline.embed_batch(&texts) {
-                let entries: Vec<(String, Vec<f32>)> = changed_notes
+            for chunk in changed_notes.chunks(batch_size) {
+                let texts_owned: Vec<String> = chunk
+                    .iter()
+                    .map(|n| {
+                        crate::preprocess::embedding_text(&n.title, &n.body, n.markup_language)
+                    })
+                    .collect();
+                let texts: Vec<&str> = texts_owned.iter().map(|s| s.as_str()).collect();
+
+                // Embed outside both locks. Notes that fail stay out of the
+                // manifest, so the next pass retries them.
+                let embeddings = match pipeline.embed_batch(&texts, Priority::Background).await {
+                    Ok(embeddings) => embeddings,
+                    Err(e) => {
+                        crate::error::record(&app, "delta_update", AppError::inference(e)).await;
+                        continue;
+                    }
+                };
+                let chunk_entries: Vec<(String, Vec<f32>)> = chunk
                     .iter()
                     .zip(embeddings)
                     .filter(|(note, _)| is_valid_joplin_id(&note.id))
@@ -432,57 +1451,298 @@ async fn run_delta_update_inner(app: tauri::AppHandle, db_path: String) {
                     s.search_index.clone()
                 };
 
-                // Take write lock on index — held for add_batch only (~50ms)
+                // Take write lock on index — held for add_batch only (~50ms).
                 if let Some(arc) = index_arc {
                     let mut index = arc.write().await;
-                    let _ = index.add_batch(entries);
+                    for (id, _) in &chunk_entries {
+                        if let Some(v) = index.vector(id) {
+                            previous.insert(id.clone(), v.to_vec());
+                        }
+                    }
+                    let added = index.add_batch(chunk_entries.clone());
                     drop(index); // release write lock before re-acquiring AppState
+                    if let Err(e) = added {
+                        crate::error::record(&app, "delta_update", AppError::internal(e)).await;
+                        continue;
+                    }
+                    index_changed = true;
                 }
 
-                // Update note_cache and scan timestamp.
+                // Update note_cache and the manifest.
                 // Also clear any tombstone entries for restored/edited notes —
-                // a note that's back in get_notes_since is live again.
-                let max_ts = changed_notes.iter().map(|n| n.updated_time).max().unwrap_or(0);
+                // a note that's back in the database is live again.
                 let state = app.state::<AppMutex>();
                 let mut s = state.lock().await;
-                for note in &changed_notes {
+                for note in chunk {
                     s.deleted_note_ids.remove(&note.id); // un-tombstone if restored
-                    s.note_cache.insert(note.id.clone(), NoteMetadata {
-                        id: note.id.clone(),
-                        title: note.title.clone(),
-                        updated_time: note.updated_time,
-                    });
+                    s.note_cache.insert(note.id.clone(), note.metadata());
+                    s.link_graph.set_links(&note.id, &note.body);
+                    if is_valid_joplin_id(&note.id) {
+                        s.manifest.insert(note);
+                    }
                 }
-                // Subtract 1ms so that a note whose updated_time exactly equals
-                // the boundary is re-checked on the next cycle (off-by-one fix).
-                s.last_scan_timestamp = s.last_scan_timestamp.max(max_ts.saturating_sub(1));
-                s.index_status.indexed_notes = s.note_cache.len();
-                s.index_status.total_notes = s.note_cache.len();
-                let _ = app.emit("index-status", &s.index_status);
                 drop(s);
+                entries.extend(chunk_entries);
+            }
 
-                // Persist the updated index so new notes survive a restart.
-                let index_path = index_file_path(&app);
-                let index_arc2 = {
-                    let state = app.state::<AppMutex>();
-                    let guard = state.lock().await;
-                    guard.search_index.clone()
-                };
-                if let Some(arc) = index_arc2 {
-                    let idx = arc.read().await;
-                    let _ = idx.save(&index_path);
-                }
+            let metadata: HashMap<&str, NoteMetadata> =
+                changed_notes.iter().map(|n| (n.id.as_str(), n.metadata())).collect();
+            let changed: Vec<crate::history::ChangedNote> = entries
+                .iter()
+                .filter_map(|(id, vector)| {
+                    Some(crate::history::ChangedNote {
+                        note: metadata.get(id.as_str())?.clone(),
+                        vector,
+                        previous: previous.get(id).map(|v| v.as_slice()),
+                    })
+                })
+                .collect();
+            check_saved_search_alerts(&app, &pipeline, &changed).await;
+        }
+    }
+
+    // 6. Persist the updated index so changes survive a restart, then the
+    // manifest so the next pass (or launch) diffs against what it now holds.
+    if index_changed {
+        let index_path = index_file_path(&app);
+        let index_arc = {
+            let state = app.state::<AppMutex>();
+            let guard = state.lock().await;
+            guard.search_index.clone()
+        };
+        if let Some(arc) = index_arc {
+            let idx = arc.read().await;
+            idx.save(&index_path).map_err(AppError::storage)?;
+        }
+    }
+    let manifest = {
+        let state = app.state::<AppMutex>();
+        let mut s = state.lock().await;
+        s.index_status.indexed_notes = s.note_cache.len();
+        s.index_status.total_notes = s.note_cache.len();
+        let _ = app.emit("index-status", &s.index_status);
+        s.manifest.clone()
+    };
+    crate::manifest::save(&manifest, &manifest_file_path(&app)).map_err(AppError::storage)
+}
+
+/// Re-evaluate saved searches that have alerts against the notes embedded in
+/// a delta pass. Emits a `saved-search-alert` event per search with new
+/// matches, plus a desktop notification if the search asks for one.
+async fn check_saved_search_alerts(
+    app: &tauri::AppHandle,
+    pipeline: &crate::embeddings::EmbeddingPipeline,
+    changed: &[crate::history::ChangedNote<'_>],
+) {
+    let searches: Vec<SavedSearch> = {
+        let state = app.state::<AppMutex>();
+        let s = state.lock().await;
+        s.history.saved.iter().filter(|s| s.alert_threshold.is_some()).cloned().collect()
+    };
+    if searches.is_empty() || changed.is_empty() {
+        return;
+    }
+    let queries: Vec<&str> = searches.iter().map(|s| s.query.as_str()).collect();
+    let query_vectors = match pipeline.embed_batch(&queries, Priority::Background).await {
+        Ok(vectors) => vectors,
+        Err(e) => {
+            crate::error::record(app, "saved_search_alerts", AppError::inference(e)).await;
+            return;
+        }
+    };
+
+    for (search, query_vector) in searches.iter().zip(&query_vectors) {
+        let matches = crate::history::new_matches(search, query_vector, changed);
+        let Some(best) = matches.first() else {
+            continue;
+        };
+        if search.notify {
+            use tauri_plugin_notification::NotificationExt;
+            let body = match matches.len() {
+                1 => best.note.title.clone(),
+                n => format!("{} and {} more", best.note.title, n - 1),
+            };
+            let _ = app
+                .notification()
+                .builder()
+                .title(format!("New match for \"{}\"", search.name))
+                .body(body)
+                .show();
+        }
+        let _ = app.emit(
+            "saved-search-alert",
+            SavedSearchAlert {
+                search_id: search.id.clone(),
+                name: search.name.clone(),
+                query: search.query.clone(),
+                matches,
+            },
+        );
+    }
+}
+
+/// Index past note revisions into the separate revision index.
+/// Incremental: only revisions not already in `revision_cache` are embedded.
+/// No-op unless `settings.index_revisions` is on.
+pub async fn run_revision_indexing(app: tauri::AppHandle, db_path: String) {
+    {
+        let state = app.state::<AppMutex>();
+        let mut s = state.lock().await;
+        if !s.settings.index_revisions || s.is_revision_indexing {
+            return;
+        }
+        s.is_revision_indexing = true;
+    }
+
+    let result = run_revision_indexing_inner(app.clone(), db_path).await;
+
+    {
+        let state = app.state::<AppMutex>();
+        state.lock().await.is_revision_indexing = false;
+    }
+    if let Err(e) = result {
+        crate::error::record(&app, "revision_indexing", e).await;
+    }
+}
+
+async fn run_revision_indexing_inner(
+    app: tauri::AppHandle,
+    db_path: String,
+) -> Result<(), AppError> {
+    // 1. Cheap check: has the revisions table changed since the last pass?
+    let conn = crate::db::open_joplin_db(&db_path).map_err(AppError::database)?;
+    let schema = crate::db::read_schema(&conn).map_err(AppError::database)?;
+    let fingerprint =
+        crate::db::revisions_fingerprint(&conn, &schema).map_err(AppError::database)?;
+    let has_index = {
+        let state = app.state::<AppMutex>();
+        let s = state.lock().await;
+        if s.revision_index.is_some() && s.revision_fingerprint == fingerprint {
+            return Ok(());
+        }
+        s.revision_index.is_some()
+    };
+
+    // 2. Replay the diff chains (pure CPU, kept off the async runtime)
+    let rows =
+        crate::db::get_revision_rows(&conn, &schema, None).map_err(AppError::database)?;
+    drop(conn);
+    let revisions = tokio::task::spawn_blocking(move || crate::revisions::reconstruct_all(rows))
+        .await
+        .map_err(AppError::internal)?;
+
+    // 3. First pass since launch: reuse the saved revision index if there is one
+    let index_path = revision_index_file_path(&app);
+    let metadata_path = index_path.with_extension("json");
+    if !has_index {
+        let saved = crate::index::SearchIndex::load(&index_path)
+            .ok()
+            .zip(crate::revisions::load_metadata(&metadata_path));
+        let (index, cache) = match saved {
+            Some((index, metadata)) => (
+                index,
+                metadata.into_iter().map(|m| (m.id.clone(), m)).collect(),
+            ),
+            None => (
+                crate::index::SearchIndex::new((revisions.len() * 2).max(2000))
+                    .map_err(AppError::internal)?,
+                HashMap::new(),
+            ),
+        };
+        let state = app.state::<AppMutex>();
+        let mut s = state.lock().await;
+        s.revision_index = Some(Arc::new(tokio::sync::RwLock::new(index)));
+        s.revision_cache = cache;
+    }
+
+    // 4. Embed revisions that aren't in the index yet, and drop the ones
+    // Joplin has expired since the last pass
+    let (pipeline, index_arc, mut indexed, batch_size) = {
+        let state = app.state::<AppMutex>();
+        let s = state.lock().await;
+        (
+            s.embedding_pipeline.clone(),
+            s.revision_index.clone(),
+            s.revision_cache.keys().cloned().collect::<HashSet<String>>(),
+            s.settings.resources.batch_size.max(1),
+        )
+    };
+    let (Some(pipeline), Some(index_arc)) = (pipeline, index_arc) else {
+        return Ok(());
+    };
+
+    let current: HashSet<&str> = revisions.iter().map(|r| r.id.as_str()).collect();
+    let expired: Vec<String> =
+        indexed.iter().filter(|id| !current.contains(id.as_str())).cloned().collect();
+    if !expired.is_empty() {
+        let mut index = index_arc.write().await;
+        for id in &expired {
+            index.remove(id);
+            indexed.remove(id);
+        }
+    }
+
+    let pending: Vec<&crate::revisions::NoteRevision> = revisions
+        .iter()
+        .filter(|r| !indexed.contains(&r.id))
+        .filter(|r| is_valid_joplin_id(&r.id) && !r.body.trim().is_empty())
+        .collect();
+
+    for chunk in pending.chunks(batch_size) {
+        let texts_owned: Vec<String> = chunk
+            .iter()
+            .map(|r| {
+                let markup = crate::preprocess::MARKUP_MARKDOWN;
+                crate::preprocess::embedding_text(&r.title, &r.body, markup)
+            })
+            .collect();
+        let texts: Vec<&str> = texts_owned.iter().map(|s| s.as_str()).collect();
+        // Revisions that fail are left out of the cache and retried next pass.
+        let embeddings = match pipeline.embed_batch(&texts, Priority::Background).await {
+            Ok(embeddings) => embeddings,
+            Err(e) => {
+                crate::error::record(&app, "revision_indexing", AppError::inference(e)).await;
+                continue;
+            }
+        };
+        let entries: Vec<(String, Vec<f32>)> = chunk
+            .iter()
+            .zip(embeddings)
+            .map(|(r, emb)| (r.id.clone(), emb))
+            .collect();
+        let added = index_arc.write().await.add_batch(entries);
+        match added {
+            Ok(()) => indexed.extend(chunk.iter().map(|r| r.id.clone())),
+            Err(e) => {
+                crate::error::record(&app, "revision_indexing", AppError::internal(e)).await
             }
         }
     }
 
-    // 5. Schedule background full rebuild if 5 minutes have elapsed
-    const REBUILD_INTERVAL: std::time::Duration = std::time::Duration::from_secs(300);
-    if last_rebuild_ts.elapsed() >= REBUILD_INTERVAL {
-        tauri::async_runtime::spawn(async move {
-            run_full_indexing(app).await;
-        });
+    // 5. Rebuild the cache from the revisions that exist now.
+    let cache: HashMap<String, RevisionMetadata> = revisions
+        .into_iter()
+        .filter(|r| indexed.contains(&r.id))
+        .map(|r| {
+            (r.id.clone(), RevisionMetadata {
+                id: r.id,
+                note_id: r.note_id,
+                title: r.title,
+                revision_time: r.revision_time,
+            })
+        })
+        .collect();
+
+    let metadata: Vec<RevisionMetadata> = cache.values().cloned().collect();
+    {
+        let state = app.state::<AppMutex>();
+        let mut s = state.lock().await;
+        s.revision_cache = cache;
+        s.revision_fingerprint = fingerprint;
     }
+
+    index_arc.read().await.save(&index_path).map_err(AppError::storage)?;
+    crate::revisions::save_metadata(&metadata, &metadata_path).map_err(AppError::storage)
 }
 
 /// Ensure the embedding pipeline is loaded (downloads model if needed).
@@ -495,32 +1755,133 @@ async fn ensure_pipeline_loaded(app: tauri::AppHandle) {
             return;
         }
         s.is_pipeline_loading = true;
+        s.index_status.enter(IndexPhase::DownloadingModel);
+        let _ = app.emit("index-status", &s.index_status);
     }
 
-    let cache_dir = app
-        .path()
-        .app_data_dir()
-        .unwrap_or_else(|_| std::path::PathBuf::from(".fastembed_cache"));
-    let cache_dir_owned = cache_dir.to_path_buf();
-
-    let pipeline = tokio::task::spawn_blocking(move || {
-        crate::embeddings::EmbeddingPipeline::new(&cache_dir_owned, false)
-    })
-    .await;
+    let pipeline = load_pipeline(&app).await;
 
     let state = app.state::<AppMutex>();
     let mut s = state.lock().await;
     s.is_pipeline_loading = false;
     match pipeline {
-        Ok(Ok(p)) => {
+        Ok(p) => {
             s.embedding_pipeline = Some(Arc::new(p));
         }
-        _ => {
-            s.index_status.error =
-                Some("Failed to load embedding model".to_string());
+        Err(e) => {
+            s.index_status.fail(e.clone());
             let _ = app.emit("index-status", &s.index_status);
+            drop(s);
+            crate::error::record(&app, "model", e).await;
+        }
+    }
+}
+
+/// Whether a settings change needs the inference workers restarted.
+fn pipeline_settings_changed(old: &Settings, new: &Settings) -> bool {
+    old.inference_workers != new.inference_workers
+        || old.resources.max_threads != new.resources.max_threads
+        || old.resources.low_priority != new.resources.low_priority
+}
+
+/// Replace the loaded pipeline with one that has the configured number of
+/// inference workers and resource limits. The old workers finish the jobs they hold and stop
+/// once the last search or indexing pass using them lets go.
+async fn restart_pipeline(app: tauri::AppHandle) {
+    {
+        let state = app.state::<AppMutex>();
+        let mut s = state.lock().await;
+        if s.embedding_pipeline.is_none() || s.is_pipeline_loading {
+            return;
+        }
+        s.is_pipeline_loading = true;
+    }
+
+    let pipeline = load_pipeline(&app).await;
+
+    let state = app.state::<AppMutex>();
+    let mut s = state.lock().await;
+    s.is_pipeline_loading = false;
+    match pipeline {
+        Ok(p) => s.embedding_pipeline = Some(Arc::new(p)),
+        // The old workers keep serving.
+        Err(e) => {
+            drop(s);
+            crate::error::record(&app, "model", e).await;
+        }
+    }
+}
+
+/// Start inference workers per the current settings, off the async runtime.
+async fn load_pipeline(
+    app: &tauri::AppHandle,
+) -> Result<crate::embeddings::EmbeddingPipeline, AppError> {
+    let (workers, limits) = {
+        let state = app.state::<AppMutex>();
+        let s = state.lock().await;
+        (
+            s.settings.inference_workers.unwrap_or(crate::embeddings::DEFAULT_WORKERS),
+            s.settings.resources.clone(),
+        )
+    };
+    let cache_dir = app
+        .path()
+        .app_data_dir()
+        .unwrap_or_else(|_| std::path::PathBuf::from(".fastembed_cache"));
+
+    let app = app.clone();
+    let pipeline = tokio::task::spawn_blocking(move || {
+        // Best-effort: if it fails, fastembed tries the download itself.
+        let started = std::time::Instant::now();
+        let mut last_report: Option<std::time::Instant> = None;
+        let downloaded = crate::embeddings::download_model(&cache_dir, |done, total| {
+            // Chunks arrive every few KB; report a few times a second.
+            if last_report.is_some_and(|t| t.elapsed() < DOWNLOAD_REPORT_INTERVAL) && done < total {
+                return;
+            }
+            last_report = Some(std::time::Instant::now());
+            report_download(&app, started.elapsed(), done, total);
+        });
+        if let Err(e) = downloaded {
+            tracing::warn!("Model download failed: {e}");
         }
+        crate::embeddings::EmbeddingPipeline::new(&cache_dir, false, workers, &limits)
+    })
+    .await
+    .map_err(AppError::internal)?;
+    pipeline.map_err(|e| AppError::Inference(format!("Failed to load embedding model: {e}")))
+}
+
+/// How often model download progress is emitted.
+const DOWNLOAD_REPORT_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Record model download progress and emit it. Runs on a blocking thread.
+fn report_download(app: &tauri::AppHandle, elapsed: std::time::Duration, done: u64, total: u64) {
+    let state = app.state::<AppMutex>();
+    let mut s = state.blocking_lock();
+    let status = &mut s.index_status;
+    status.downloaded_bytes = done;
+    status.download_total_bytes = Some(total);
+    if status.phase == IndexPhase::DownloadingModel {
+        status.progress = Some(done as f32 / total.max(1) as f32);
+        let rate = done as f32 / elapsed.as_secs_f32().max(0.001);
+        status.eta_secs = (rate > 0.0).then(|| (total.saturating_sub(done) as f32 / rate) as u64);
     }
+    let _ = app.emit("index-status", &s.index_status);
+}
+
+/// Resolve link-graph IDs to cached note metadata, skipping anything that
+/// isn't a live, indexed note.
+fn linked_notes<'a>(
+    s: &crate::AppState,
+    ids: impl Iterator<Item = &'a String>,
+) -> Vec<NoteMetadata> {
+    let mut notes: Vec<NoteMetadata> = ids
+        .filter(|id| !s.deleted_note_ids.contains(*id))
+        .filter_map(|id| s.note_cache.get(id).cloned())
+        .collect();
+    notes.sort_by_cached_key(|n| n.title.to_lowercase());
+    notes
 }
 
 /// Validate that a note ID is a 32-character lowercase hex string (Joplin UUID format).
@@ -531,28 +1892,267 @@ fn is_valid_joplin_id(id: &str) -> bool {
 /// Open a note in the Joplin desktop app via its URL protocol handler.
 /// Works on Linux (xdg-open), Windows (ShellExecute), macOS (open).
 #[tauri::command]
-pub async fn open_in_joplin(note_id: String) -> Result<(), String> {
+pub async fn open_in_joplin(note_id: String, app: tauri::AppHandle) -> Result<(), AppError> {
     if !is_valid_joplin_id(&note_id) {
-        return Err("invalid_note_id".to_string());
+        return Err(AppError::InvalidNoteId);
     }
     let url = format!("joplin://x-callback-url/openNote?id={}", note_id);
-    open::that_detached(url).map_err(|e| e.to_string())
+    open::that_detached(url).map_err(AppError::launch)?;
+    record_open(&app, &note_id).await;
+    Ok(())
+}
+
+/// Pin or unpin a note. Pinned notes get the `pinned` ranking boost.
+/// Fails with `AppError::InvalidNoteId` if `note_id` isn't a Joplin note ID.
+#[tauri::command]
+pub async fn pin_note(
+    note_id: String,
+    pinned: bool,
+    state: tauri::State<'_, AppMutex>,
+    app: tauri::AppHandle,
+) -> Result<(), AppError> {
+    if !is_valid_joplin_id(&note_id) {
+        return Err(AppError::InvalidNoteId);
+    }
+    let usage = {
+        let mut s = state.lock().await;
+        if pinned {
+            s.usage.pinned.insert(note_id);
+        } else {
+            s.usage.pinned.remove(&note_id);
+        }
+        s.usage.clone()
+    };
+    crate::usage::save(&usage, &usage_file_path(&app)).map_err(AppError::storage)
+}
+
+/// IDs of all pinned notes.
+#[tauri::command]
+pub async fn get_pinned_notes(state: tauri::State<'_, AppMutex>) -> Result<Vec<String>, AppError> {
+    Ok(state.lock().await.usage.pinned.iter().cloned().collect())
+}
+
+/// Recent queries, newest first. Empty when history is disabled.
+#[tauri::command]
+pub async fn get_search_history(
+    state: tauri::State<'_, AppMutex>,
+) -> Result<Vec<HistoryEntry>, AppError> {
+    Ok(state.lock().await.history.recent.clone())
+}
+
+/// Forget all recent queries. Saved searches are kept.
+#[tauri::command]
+pub async fn clear_search_history(
+    state: tauri::State<'_, AppMutex>,
+    app: tauri::AppHandle,
+) -> Result<(), AppError> {
+    let history = {
+        let mut s = state.lock().await;
+        s.history.recent.clear();
+        s.history.clone()
+    };
+    crate::history::save(&history, &history_file_path(&app)).map_err(AppError::storage)
+}
+
+/// All saved searches, oldest first.
+#[tauri::command]
+pub async fn list_saved_searches(
+    state: tauri::State<'_, AppMutex>,
+) -> Result<Vec<SavedSearch>, AppError> {
+    Ok(state.lock().await.history.saved.clone())
+}
+
+/// Save a query and its filters under a name.
+#[tauri::command]
+pub async fn save_search(
+    name: String,
+    query: String,
+    options: Option<SearchOptions>,
+    state: tauri::State<'_, AppMutex>,
+    app: tauri::AppHandle,
+) -> Result<SavedSearch, AppError> {
+    if name.trim().is_empty() || query.trim().is_empty() {
+        return Err(AppError::EmptySavedSearch);
+    }
+    let (saved, history) = {
+        let mut s = state.lock().await;
+        let saved = s.history.save_search(
+            &name,
+            &query,
+            options.unwrap_or_default(),
+            crate::usage::now_ms(),
+        );
+        (saved, s.history.clone())
+    };
+    crate::history::save(&history, &history_file_path(&app)).map_err(AppError::storage)?;
+    Ok(saved)
+}
+
+/// Turn alerts for a saved search on or off. With `alert_threshold` set, new
+/// or edited notes scoring at least that much raise a `saved-search-alert`
+/// event (and a desktop notification if `notify`).
+#[tauri::command]
+pub async fn set_saved_search_alert(
+    id: String,
+    alert_threshold: Option<f32>,
+    notify: bool,
+    state: tauri::State<'_, AppMutex>,
+    app: tauri::AppHandle,
+) -> Result<SavedSearch, AppError> {
+    let (saved, history) = {
+        let mut s = state.lock().await;
+        let saved = s
+            .history
+            .set_alert(&id, alert_threshold, notify)
+            .ok_or(AppError::SavedSearchNotFound)?;
+        (saved, s.history.clone())
+    };
+    crate::history::save(&history, &history_file_path(&app)).map_err(AppError::storage)?;
+    Ok(saved)
+}
+
+/// Delete a saved search.
+#[tauri::command]
+pub async fn delete_saved_search(
+    id: String,
+    state: tauri::State<'_, AppMutex>,
+    app: tauri::AppHandle,
+) -> Result<(), AppError> {
+    let history = {
+        let mut s = state.lock().await;
+        if !s.history.delete_saved(&id) {
+            return Err(AppError::SavedSearchNotFound);
+        }
+        s.history.clone()
+    };
+    crate::history::save(&history, &history_file_path(&app)).map_err(AppError::storage)
+}
+
+/// Run a saved search with its stored filters.
+#[tauri::command]
+pub async fn run_saved_search(
+    id: String,
+    state: tauri::State<'_, AppMutex>,
+) -> Result<Vec<SearchResult>, AppError> {
+    let saved = state
+        .lock()
+        .await
+        .history
+        .saved
+        .iter()
+        .find(|s| s.id == id)
+        .cloned()
+        .ok_or(AppError::SavedSearchNotFound)?;
+    let snapshot = SearchSnapshot::take(&state).await?;
+    let query_embedding =
+        snapshot.pipeline.embed_one(&saved.query).await.map_err(AppError::inference)?;
+    run_search(snapshot, &query_embedding, &saved.query, &saved.options, &HashSet::new()).await
+}
+
+/// Add a query to the search history and persist it (best-effort).
+/// Does nothing when history is disabled.
+async fn record_query(app: &tauri::AppHandle, query: &str, options: &SearchOptions) {
+    static HISTORY_WRITE: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    {
+        let state = app.state::<AppMutex>();
+        let mut s = state.lock().await;
+        if s.settings.disable_history || query.trim().is_empty() {
+            return;
+        }
+        s.history.record(query, options, crate::usage::now_ms());
+    }
+    let path = history_file_path(app);
+    save_in_background(app, &HISTORY_WRITE, path, |s| s.history.clone(), crate::history::save);
+}
+
+/// Remember that a note was opened and persist usage data (best-effort).
+async fn record_open(app: &tauri::AppHandle, note_id: &str) {
+    static USAGE_WRITE: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    {
+        let state = app.state::<AppMutex>();
+        state.lock().await.usage.record_open(note_id, crate::usage::now_ms());
+    }
+    let path = usage_file_path(app);
+    save_in_background(app, &USAGE_WRITE, path, |s| s.usage.clone(), crate::usage::save);
+}
+
+/// Persist part of the app state on a blocking thread (best-effort), so
+/// commands called on every keystroke or click don't wait on the disk.
+/// `save` runs with `lock` held and reads the state at that point, so a write
+/// that started earlier can't land after, and overwrite, a newer one.
+fn save_in_background<T: 'static>(
+    app: &tauri::AppHandle,
+    lock: &'static std::sync::Mutex<()>,
+    path: std::path::PathBuf,
+    snapshot: fn(&crate::AppState) -> T,
+    save: fn(&T, &std::path::Path) -> anyhow::Result<()>,
+) {
+    let app = app.clone();
+    tokio::task::spawn_blocking(move || {
+        let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+        let data = snapshot(&app.state::<AppMutex>().blocking_lock());
+        if let Err(e) = save(&data, &path) {
+            tracing::warn!("Failed to save {}: {e}", path.display());
+        }
+    });
 }
 
 /// Open an external http/https URL in the system default browser.
 #[tauri::command]
-pub async fn open_external_url(url: String) -> Result<(), String> {
+pub async fn open_external_url(url: String) -> Result<(), AppError> {
     if !url.starts_with("http://") && !url.starts_with("https://") {
-        return Err("only_http_allowed".to_string());
+        return Err(AppError::OnlyHttpAllowed);
     }
-    open::that_detached(url).map_err(|e| e.to_string())
+    open::that_detached(url).map_err(AppError::launch)
 }
 
 /// Path where the HNSW index binary is persisted.
 pub fn index_file_path(app: &tauri::AppHandle) -> std::path::PathBuf {
+    data_file_path(app, "index.bin")
+}
+
+/// Path where the revision HNSW index is persisted. Its metadata is saved
+/// next to it with a `.json` extension.
+pub fn revision_index_file_path(app: &tauri::AppHandle) -> std::path::PathBuf {
+    data_file_path(app, "revisions.bin")
+}
+
+/// Path of the user settings file.
+pub fn settings_file_path(app: &tauri::AppHandle) -> std::path::PathBuf {
+    data_file_path(app, "settings.json")
+}
+
+/// Path of the note open/pin history used for ranking.
+pub fn usage_file_path(app: &tauri::AppHandle) -> std::path::PathBuf {
+    data_file_path(app, "usage.json")
+}
+
+/// Path of the manifest of notes held by the HNSW index.
+pub fn manifest_file_path(app: &tauri::AppHandle) -> std::path::PathBuf {
+    data_file_path(app, "manifest.json")
+}
+
+/// Path of the partially built index a cancelled or interrupted full build
+/// resumes from. Its manifest is at `checkpoint_manifest_file_path`.
+pub fn checkpoint_index_file_path(app: &tauri::AppHandle) -> std::path::PathBuf {
+    data_file_path(app, "index.partial.bin")
+}
+
+/// Path of the manifest of notes embedded into the checkpoint index.
+pub fn checkpoint_manifest_file_path(app: &tauri::AppHandle) -> std::path::PathBuf {
+    data_file_path(app, "manifest.partial.json")
+}
+
+/// Path of the search history and saved searches.
+pub fn history_file_path(app: &tauri::AppHandle) -> std::path::PathBuf {
+    data_file_path(app, "history.json")
+}
+
+/// Files owned by this app live together in `<app_data_dir>/joplin-smart-search/`.
+fn data_file_path(app: &tauri::AppHandle, name: &str) -> std::path::PathBuf {
     app.path()
         .app_data_dir()
         .unwrap_or_else(|_| std::path::PathBuf::from("."))
         .join("joplin-smart-search")
-        .join("index.bin")
+        .join(name)
 }
@@ -1,45 +1,181 @@
 use anyhow::Result;
 use rusqlite::Connection;
-use std::path::PathBuf;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 
-use crate::types::Note;
+use crate::revisions::RevisionRow;
+use crate::types::{DbCandidate, Note, Settings};
+
+/// A Joplin config directory that may hold one or more profiles.
+struct ConfigRoot {
+    dir: PathBuf,
+    install: &'static str,
+}
+
+/// Profile list as stored in `<config root>/profiles.json`.
+/// Only present once the user has created a second profile in Joplin.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfilesFile {
+    #[serde(default)]
+    profiles: Vec<ProfileEntry>,
+    #[serde(default)]
+    current_profile_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProfileEntry {
+    id: String,
+    name: String,
+}
+
+/// Joplin's id for the profile that lives directly in the config root.
+const DEFAULT_PROFILE_ID: &str = "default";
+
+/// Enumerate every Joplin database on this machine, one entry per profile per
+/// install location. Sorted so the most likely choice comes first: Joplin's
+/// current profile, then the most recently modified database.
+pub fn detect_joplin_db_candidates() -> Vec<DbCandidate> {
+    let mut seen = HashSet::new();
+    let mut candidates = Vec::new();
+
+    for root in config_roots() {
+        for (profile_id, profile_name, is_current) in list_profiles(&root.dir) {
+            let dir = if profile_id == DEFAULT_PROFILE_ID {
+                root.dir.clone()
+            } else {
+                root.dir.join(format!("profile-{profile_id}"))
+            };
+            let path = dir.join("database.sqlite");
+            if !path.is_file() {
+                continue;
+            }
+            // The same file can be reachable through several roots
+            // (e.g. XDG_CONFIG_HOME pointing at ~/.config).
+            let key = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+            if !seen.insert(key) {
+                continue;
+            }
+            let path_str = path.to_string_lossy().to_string();
+            candidates.push(DbCandidate {
+                note_count: count_notes(&path_str),
+                modified_time: db_modified_time(&path_str),
+                path: path_str,
+                profile_id,
+                profile_name,
+                install: root.install.to_string(),
+                is_current_profile: is_current,
+            });
+        }
+    }
+
+    candidates.sort_by(|a, b| {
+        b.is_current_profile
+            .cmp(&a.is_current_profile)
+            .then(b.modified_time.cmp(&a.modified_time))
+    });
+    candidates
+}
+
+/// Config directories where Joplin (or a sandboxed build of it) keeps its data.
+fn config_roots() -> Vec<ConfigRoot> {
+    #[allow(unused_mut)]
+    let mut roots = Vec::new();
 
-/// Auto-detect the Joplin SQLite database path.
-/// Falls back to None if not found — caller should prompt user to browse.
-pub fn detect_joplin_db_path() -> Option<PathBuf> {
     #[cfg(target_os = "linux")]
     {
-        if let Some(home) = std::env::var("HOME").ok() {
-            let path = PathBuf::from(&home).join(".config/joplin-desktop/database.sqlite");
-            if path.exists() {
-                return Some(path);
-            }
-            let path = PathBuf::from(&home).join(".config/joplin/database.sqlite");
-            if path.exists() {
-                return Some(path);
+        if let Ok(xdg) = std::env::var("XDG_CONFIG_HOME") {
+            if !xdg.is_empty() {
+                roots.push(ConfigRoot { dir: PathBuf::from(xdg).join("joplin-desktop"), install: "Joplin" });
             }
         }
+        if let Ok(home) = std::env::var("HOME") {
+            let home = PathBuf::from(home);
+            roots.push(ConfigRoot { dir: home.join(".config/joplin-desktop"), install: "Joplin" });
+            // Terminal app
+            roots.push(ConfigRoot { dir: home.join(".config/joplin"), install: "Joplin CLI" });
+            roots.push(ConfigRoot {
+                dir: home.join(".var/app/net.cozic.joplin_desktop/config/joplin-desktop"),
+                install: "Flatpak",
+            });
+            roots.push(ConfigRoot {
+                dir: home.join("snap/joplin-desktop/current/.config/joplin-desktop"),
+                install: "Snap",
+            });
+        }
     }
 
     #[cfg(target_os = "windows")]
     {
-        // Standard Joplin Desktop install: %USERPROFILE%\.config\joplin-desktop\database.sqlite
-        if let Some(profile) = std::env::var("USERPROFILE").ok() {
-            let path = PathBuf::from(&profile).join(".config").join("joplin-desktop").join("database.sqlite");
-            if path.exists() {
-                return Some(path);
-            }
+        // Standard Joplin Desktop install: %USERPROFILE%\.config\joplin-desktop
+        if let Ok(profile) = std::env::var("USERPROFILE") {
+            roots.push(ConfigRoot {
+                dir: PathBuf::from(profile).join(".config").join("joplin-desktop"),
+                install: "Joplin",
+            });
         }
-        // Fallback: older/portable Joplin: %APPDATA%\Joplin\database.sqlite
-        if let Some(app_data) = std::env::var("APPDATA").ok() {
-            let path = PathBuf::from(&app_data).join("Joplin").join("database.sqlite");
-            if path.exists() {
-                return Some(path);
-            }
+        // Fallback: older/portable Joplin: %APPDATA%\Joplin
+        if let Ok(app_data) = std::env::var("APPDATA") {
+            roots.push(ConfigRoot { dir: PathBuf::from(app_data).join("Joplin"), install: "Joplin (portable)" });
+        }
+    }
+
+    roots
+}
+
+/// List `(profile_id, profile_name, is_current)` for a config root.
+/// Without a readable `profiles.json` only the default profile exists.
+fn list_profiles(root: &Path) -> Vec<(String, String, bool)> {
+    let parsed = std::fs::read_to_string(root.join("profiles.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str::<ProfilesFile>(&s).ok());
+
+    match parsed {
+        Some(file) if !file.profiles.is_empty() => {
+            let current = file
+                .current_profile_id
+                .unwrap_or_else(|| DEFAULT_PROFILE_ID.to_string());
+            file.profiles
+                .into_iter()
+                .map(|p| {
+                    let is_current = p.id == current;
+                    (p.id, p.name, is_current)
+                })
+                .collect()
         }
+        _ => vec![(DEFAULT_PROFILE_ID.to_string(), "Default".to_string(), true)],
     }
+}
+
+/// Number of live notes in a candidate database, or None if it can't be read.
+fn count_notes(path: &str) -> Option<usize> {
+    let conn = open_joplin_db(path).ok()?;
+    let schema = read_schema(&conn).ok()?;
+    conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM notes WHERE {}",
+            schema.scope_sql(NoteScope::default())
+        ),
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .ok()
+    .map(|n| n as usize)
+}
 
-    None
+/// Last modification of the database as Unix ms. Checks the WAL file too,
+/// since recent writes only reach the main file after a checkpoint.
+fn db_modified_time(path: &str) -> Option<i64> {
+    let mtime = |p: &str| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+    let newest = match (mtime(path), mtime(&format!("{path}-wal"))) {
+        (Some(a), Some(b)) => a.max(b),
+        (a, b) => a.or(b)?,
+    };
+    newest
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_millis() as i64)
 }
 
 /// Open the Joplin SQLite database in read-only mode.
@@ -55,124 +191,496 @@ pub fn open_joplin_db(path: &str) -> Result<Connection> {
     Ok(conn)
 }
 
-/// Fetch all non-conflict notes. Used for initial index build.
-pub fn get_all_notes(conn: &Connection) -> Result<Vec<Note>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, title, body, updated_time
+/// Columns every supported Joplin version has on the `notes` table.
+const REQUIRED_NOTE_COLUMNS: &[&str] = &["id", "title", "body", "updated_time"];
+
+/// Newest Joplin schema version this app has been checked against.
+/// Newer databases are still opened (required columns are validated
+/// individually), but a warning is logged.
+const NEWEST_KNOWN_SCHEMA: i64 = 48;
+
+/// What an opened Joplin database supports, derived from its schema.
+/// Queries adapt to this so older databases degrade instead of failing.
+#[derive(Debug, Clone)]
+pub struct DbSchema {
+    /// Value of the `version` table (Joplin migration number).
+    pub version: i64,
+    /// `notes.deleted_time` exists (trash, added in Joplin 3.0).
+    /// Without it every note is treated as live.
+    pub has_trash: bool,
+    /// `folders.deleted_time` exists, so notes in a trashed notebook can be
+    /// treated as trashed even if their own `deleted_time` isn't set.
+    pub has_folder_trash: bool,
+    /// `notes.is_conflict` exists. Without it no note is treated as a conflict.
+    pub has_conflicts: bool,
+    /// `notes.is_todo` / `notes.todo_completed` exist.
+    pub has_todos: bool,
+    /// The `revisions` table (note history) exists.
+    pub has_revisions: bool,
+    /// All columns of the `notes` table, for optional metadata columns.
+    note_columns: HashSet<String>,
+}
+
+/// Optional note categories read in addition to regular notes.
+/// Built from the user's settings; all excluded by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NoteScope {
+    /// Notes in Joplin's trash.
+    pub trash: bool,
+    /// Conflict copies created by sync.
+    pub conflicts: bool,
+    /// To-dos with an empty body, indexed by title alone.
+    pub empty_todos: bool,
+}
+
+impl From<&Settings> for NoteScope {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            trash: settings.include_trash,
+            conflicts: settings.include_conflicts,
+            empty_todos: settings.include_empty_todos,
+        }
+    }
+}
+
+/// Characters a body may consist of and still count as empty. Matches the
+/// `trim(body, char(32, 9, 10, 13))` of `DbSchema::body_sql`, so notes read
+/// with and without their body agree on what is indexed.
+const BLANK: [char; 4] = [' ', '\t', '\n', '\r'];
+
+impl NoteScope {
+    /// Whether a fetched note has enough text to be indexed.
+    fn keeps(&self, note: &Note) -> bool {
+        !note.body.trim_matches(BLANK).is_empty() || (self.empty_todos && note.is_todo)
+    }
+}
+
+impl DbSchema {
+    /// Column list read by `row_to_note`. Columns missing from older schemas
+    /// read as 0 / empty string.
+    fn note_columns_sql(&self) -> String {
+        [
+            ("id", "id"),
+            ("title", "title"),
+            ("body", "body"),
+            ("updated_time", "updated_time"),
+            ("is_todo", "0"),
+            ("todo_completed", "0"),
+            ("is_conflict", "0"),
+            ("deleted_time", "0"), // replaced by `trash_sql` below
+            ("created_time", "0"),
+            ("user_updated_time", "updated_time"),
+            ("source_url", "''"),
+            ("author", "''"),
+            ("latitude", "0"),
+            ("longitude", "0"),
+            ("markup_language", "1"),
+            ("parent_id", "''"),
+        ]
+        .iter()
+        .map(|(column, fallback)| {
+            if *column == "deleted_time" {
+                self.trash_sql()
+            } else if self.note_columns.contains(*column) {
+                column.to_string()
+            } else {
+                fallback.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+
+    /// SQL expression that is true for trashed notes: deleted themselves, or
+    /// inside a trashed notebook (at any depth).
+    fn trash_sql(&self) -> String {
+        if !self.has_trash {
+            return "0".to_string();
+        }
+        if !self.has_folder_trash {
+            return "(deleted_time != 0)".to_string();
+        }
+        "(deleted_time != 0 OR parent_id IN (
+             WITH RECURSIVE trashed(id) AS (
+                 SELECT id FROM folders WHERE deleted_time != 0
+                 UNION
+                 SELECT f.id FROM folders f JOIN trashed t ON f.parent_id = t.id
+             )
+             SELECT id FROM trashed))"
+            .to_string()
+    }
+
+    /// SQL condition matching the notes in `scope`: conflict copies and trashed
+    /// notes are excluded unless the scope asks for them.
+    fn scope_sql(&self, scope: NoteScope) -> String {
+        let mut conds = vec![];
+        if self.has_conflicts && !scope.conflicts {
+            conds.push("is_conflict = 0");
+        }
+        let live;
+        if self.has_trash && !scope.trash {
+            live = format!("NOT {}", self.trash_sql());
+            conds.push(&live);
+        }
+        if conds.is_empty() {
+            return "1".to_string();
+        }
+        conds.join(" AND ")
+    }
+
+    /// SQL condition on the body: non-empty, or any to-do if empty to-dos are in scope.
+    fn body_sql(&self, scope: NoteScope) -> &'static str {
+        if scope.empty_todos && self.has_todos {
+            "(trim(body, char(32, 9, 10, 13)) != '' OR is_todo = 1)"
+        } else {
+            "trim(body, char(32, 9, 10, 13)) != ''"
+        }
+    }
+}
+
+/// Map a row selected with `DbSchema::note_columns_sql` to a Note.
+fn row_to_note(row: &rusqlite::Row) -> rusqlite::Result<Note> {
+    Ok(Note {
+        id: row.get::<_, String>(0)?,
+        title: row.get::<_, String>(1).unwrap_or_default(),
+        body: row.get::<_, String>(2).unwrap_or_default(),
+        updated_time: row.get::<_, i64>(3)?,
+        is_todo: row.get::<_, i64>(4).unwrap_or(0) != 0,
+        todo_completed: row.get::<_, i64>(5).unwrap_or(0),
+        is_conflict: row.get::<_, i64>(6).unwrap_or(0) != 0,
+        in_trash: row.get::<_, i64>(7).unwrap_or(0) != 0,
+        created_time: row.get::<_, i64>(8).unwrap_or(0),
+        user_updated_time: row.get::<_, i64>(9).unwrap_or(0),
+        source_url: row.get::<_, String>(10).unwrap_or_default(),
+        author: row.get::<_, String>(11).unwrap_or_default(),
+        latitude: row.get::<_, f64>(12).unwrap_or(0.0),
+        longitude: row.get::<_, f64>(13).unwrap_or(0.0),
+        markup_language: row.get::<_, i64>(14).unwrap_or(1),
+        parent_id: row.get::<_, String>(15).unwrap_or_default(),
+    })
+}
+
+/// Read the schema version and validate that everything the app queries exists.
+/// The error message names the exact incompatibility and is shown to the user.
+pub fn read_schema(conn: &Connection) -> Result<DbSchema> {
+    let has_version_table: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'version'",
+        [],
+        |row| row.get::<_, i64>(0).map(|n| n > 0),
+    )?;
+    if !has_version_table {
+        anyhow::bail!("Not a Joplin database: the `version` table is missing");
+    }
+    let version: i64 = conn
+        .query_row("SELECT version FROM version LIMIT 1", [], |row| row.get(0))
+        .map_err(|e| anyhow::anyhow!("Could not read the Joplin schema version: {e}"))?;
+
+    let columns = table_columns(conn, "notes")?;
+    if columns.is_empty() {
+        anyhow::bail!("Incompatible Joplin database (schema {version}): the `notes` table is missing");
+    }
+    let missing: Vec<&str> = REQUIRED_NOTE_COLUMNS
+        .iter()
+        .copied()
+        .filter(|c| !columns.contains(*c))
+        .collect();
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "Incompatible Joplin database (schema {version}): `notes` is missing column(s) {}",
+            missing.join(", ")
+        );
+    }
+
+    if version > NEWEST_KNOWN_SCHEMA {
+        tracing::warn!("Joplin schema {version} is newer than tested ({NEWEST_KNOWN_SCHEMA})");
+    }
+
+    Ok(DbSchema {
+        version,
+        has_trash: columns.contains("deleted_time"),
+        has_folder_trash: columns.contains("deleted_time")
+            && columns.contains("parent_id")
+            && table_columns(conn, "folders")?.contains("deleted_time"),
+        has_conflicts: columns.contains("is_conflict"),
+        has_todos: columns.contains("is_todo") && columns.contains("todo_completed"),
+        has_revisions: !table_columns(conn, "revisions")?.is_empty(),
+        note_columns: columns,
+    })
+}
+
+/// Column names of `table`. Empty if the table does not exist.
+fn table_columns(conn: &Connection, table: &str) -> Result<HashSet<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(columns)
+}
+
+/// Fetch all notes in `scope` with indexable text. Used for initial index build.
+pub fn get_all_notes(conn: &Connection, schema: &DbSchema, scope: NoteScope) -> Result<Vec<Note>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}
          FROM notes
-         WHERE is_conflict = 0
-           AND deleted_time = 0
-           AND trim(body) != ''
+         WHERE {}
+           AND {}
          ORDER BY updated_time DESC",
-    )?;
+        schema.note_columns_sql(),
+        schema.scope_sql(scope),
+        schema.body_sql(scope),
+    ))?;
 
     let notes = stmt
-        .query_map([], |row| {
-            Ok(Note {
-                id: row.get::<_, String>(0)?,
-                title: row.get::<_, String>(1).unwrap_or_default(),
-                body: row.get::<_, String>(2).unwrap_or_default(),
-                updated_time: row.get::<_, i64>(3)?,
-            })
-        })?
+        .query_map([], row_to_note)?
         .filter_map(|r| {
             r.map_err(|e| tracing::warn!("Skipping malformed row: {e}"))
              .ok()
         })
-        .filter(|n| !n.body.trim().is_empty())
+        .filter(|n| scope.keeps(n))
         .collect();
 
     Ok(notes)
 }
 
-/// Fetch a single note by ID (including body). Returns None if not found.
-pub fn get_note_by_id(conn: &Connection, id: &str) -> Result<Option<Note>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, title, body, updated_time
+/// Fetch a single note by ID (including body). Returns None if not found
+/// or outside `scope`.
+pub fn get_note_by_id(
+    conn: &Connection,
+    schema: &DbSchema,
+    scope: NoteScope,
+    id: &str,
+) -> Result<Option<Note>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}
          FROM notes
          WHERE id = ?1
-           AND is_conflict = 0
-           AND deleted_time = 0",
-    )?;
-    let mut rows = stmt.query_map([id], |row| {
-        Ok(Note {
-            id: row.get::<_, String>(0)?,
-            title: row.get::<_, String>(1).unwrap_or_default(),
-            body: row.get::<_, String>(2).unwrap_or_default(),
-            updated_time: row.get::<_, i64>(3)?,
-        })
-    })?;
+           AND {}",
+        schema.note_columns_sql(),
+        schema.scope_sql(scope),
+    ))?;
+    let mut rows = stmt.query_map([id], row_to_note)?;
     Ok(rows.next().transpose()?)
 }
 
-/// Cheaply check whether any notes have changed or been deleted since `since_ms`.
-/// Used by the watcher before committing to a full re-embed.
-pub fn has_notes_since(conn: &Connection, since_ms: i64) -> Result<bool> {
-    let changed: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM notes
-         WHERE is_conflict = 0
-           AND deleted_time = 0
-           AND updated_time > ?1",
-        [since_ms],
-        |row| row.get(0),
-    )?;
-    if changed > 0 {
-        return Ok(true);
-    }
-    let deleted: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM notes
-         WHERE is_conflict = 0
-           AND deleted_time > ?1",
-        [since_ms],
-        |row| row.get(0),
-    )?;
-    Ok(deleted > 0)
+/// Change markers of a note, compared against the index manifest to find
+/// notes that changed since they were embedded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoteVersion {
+    pub updated_time: i64,
+    pub in_trash: bool,
 }
 
-/// Fetch IDs of notes soft-deleted after `since_ms`.
-/// Joplin sets deleted_time to a non-zero Unix ms timestamp on soft-delete.
-pub fn get_deleted_note_ids_since(conn: &Connection, since_ms: i64) -> Result<Vec<String>> {
-    let mut stmt = conn.prepare(
-        "SELECT id
+/// Version of every note in `scope` with indexable text, keyed by ID.
+/// Cheap: bodies are not read out. The delta update diffs this against the
+/// manifest of what the index holds.
+pub fn get_note_versions(
+    conn: &Connection,
+    schema: &DbSchema,
+    scope: NoteScope,
+) -> Result<HashMap<String, NoteVersion>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, updated_time, {}
          FROM notes
-         WHERE is_conflict = 0
-           AND deleted_time > ?1",
-    )?;
-    let ids = stmt
-        .query_map([since_ms], |row| row.get::<_, String>(0))?
+         WHERE {}
+           AND {}",
+        schema.trash_sql(),
+        schema.scope_sql(scope),
+        schema.body_sql(scope),
+    ))?;
+    let versions = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                NoteVersion {
+                    updated_time: row.get(1)?,
+                    in_trash: row.get::<_, i64>(2).unwrap_or(0) != 0,
+                },
+            ))
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(versions)
+}
+
+/// Fetch the notes in `scope` with the given IDs (including body).
+/// IDs that don't exist or are out of scope are skipped.
+pub fn get_notes_by_ids(
+    conn: &Connection,
+    schema: &DbSchema,
+    scope: NoteScope,
+    ids: &[String],
+) -> Result<Vec<Note>> {
+    // Stay well below SQLite's bound-parameter limit.
+    const CHUNK: usize = 500;
+    let mut notes = Vec::with_capacity(ids.len());
+    for chunk in ids.chunks(CHUNK) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
+             FROM notes
+             WHERE id IN ({placeholders})
+               AND {}
+               AND {}",
+            schema.note_columns_sql(),
+            schema.scope_sql(scope),
+            schema.body_sql(scope),
+        ))?;
+        notes.extend(
+            stmt.query_map(rusqlite::params_from_iter(chunk), row_to_note)?
+                .filter_map(|r| {
+                    r.map_err(|e| tracing::warn!("Skipping malformed row: {e}"))
+                     .ok()
+                })
+                .filter(|n| scope.keeps(n)),
+        );
+    }
+    Ok(notes)
+}
+
+/// Full path of every notebook ("Parent / Child"), keyed by folder UUID.
+/// Encrypted notebooks have an empty title and show up as "".
+pub fn get_notebook_paths(conn: &Connection) -> Result<HashMap<String, String>> {
+    let mut stmt = conn.prepare("SELECT id, title, parent_id FROM folders")?;
+    let folders: HashMap<String, (String, String)> = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                (
+                    row.get::<_, String>(1).unwrap_or_default(),
+                    row.get::<_, String>(2).unwrap_or_default(),
+                ),
+            ))
+        })?
         .filter_map(|r| r.ok())
         .collect();
-    Ok(ids)
+
+    let paths = folders
+        .iter()
+        .map(|(id, (title, parent))| {
+            let mut parts = vec![title.as_str()];
+            let mut parent = parent;
+            // Bounded walk: a corrupt parent cycle must not hang.
+            while let Some((t, p)) = folders.get(parent).filter(|_| parts.len() < 32) {
+                parts.push(t);
+                parent = p;
+            }
+            parts.reverse();
+            (id.clone(), parts.join(" / "))
+        })
+        .collect();
+    Ok(paths)
 }
 
-/// Fetch only notes updated after `since_ms` (Unix ms timestamp).
-/// Used by the delta update path to embed only changed notes.
-pub fn get_notes_since(conn: &Connection, since_ms: i64) -> Result<Vec<Note>> {
+/// Tag titles of every tagged note, keyed by note UUID.
+pub fn get_note_tags(conn: &Connection) -> Result<HashMap<String, Vec<String>>> {
     let mut stmt = conn.prepare(
-        "SELECT id, title, body, updated_time
-         FROM notes
-         WHERE is_conflict = 0
-           AND deleted_time = 0
-           AND trim(body) != ''
-           AND updated_time > ?1
-         ORDER BY updated_time DESC",
+        "SELECT note_tags.note_id, tags.title
+         FROM note_tags
+         JOIN tags ON tags.id = note_tags.tag_id
+         WHERE tags.title != ''",
     )?;
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    for row in stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .filter_map(|r| r.ok())
+    {
+        tags.entry(row.0).or_default().push(row.1);
+    }
+    Ok(tags)
+}
 
-    let notes = stmt
-        .query_map([since_ms], |row| {
-            Ok(Note {
+/// Cheap change marker for the revisions table: (row count, newest updated_time).
+/// Lets the revision indexer skip replaying history when nothing was added.
+pub fn revisions_fingerprint(conn: &Connection, schema: &DbSchema) -> Result<(i64, i64)> {
+    if !schema.has_revisions {
+        return Ok((0, 0));
+    }
+    let fingerprint = conn.query_row(
+        "SELECT COUNT(*), COALESCE(MAX(updated_time), 0) FROM revisions",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(fingerprint)
+}
+
+/// Fetch unencrypted note revisions, grouped by note and oldest first —
+/// the order `revisions::reconstruct_all` needs to replay the diff chains.
+/// Pass `note_id` to fetch the history of a single note.
+pub fn get_revision_rows(
+    conn: &Connection,
+    schema: &DbSchema,
+    note_id: Option<&str>,
+) -> Result<Vec<RevisionRow>> {
+    if !schema.has_revisions {
+        return Ok(vec![]);
+    }
+    let mut stmt = conn.prepare(
+        "SELECT id, item_id, item_updated_time, title_diff, body_diff
+         FROM revisions
+         WHERE item_type = 1
+           AND encryption_applied = 0
+           AND (?1 IS NULL OR item_id = ?1)
+         ORDER BY item_id, item_updated_time ASC",
+    )?;
+    let rows = stmt
+        .query_map([note_id], |row| {
+            Ok(RevisionRow {
                 id: row.get::<_, String>(0)?,
-                title: row.get::<_, String>(1).unwrap_or_default(),
-                body: row.get::<_, String>(2).unwrap_or_default(),
-                updated_time: row.get::<_, i64>(3)?,
+                note_id: row.get::<_, String>(1)?,
+                item_updated_time: row.get::<_, i64>(2)?,
+                title_diff: row.get::<_, String>(3).unwrap_or_default(),
+                body_diff: row.get::<_, String>(4).unwrap_or_default(),
             })
         })?
         .filter_map(|r| {
-            r.map_err(|e| tracing::warn!("Skipping malformed row: {e}"))
+            r.map_err(|e| tracing::warn!("Skipping malformed revision row: {e}"))
              .ok()
         })
-        .filter(|n| !n.body.trim().is_empty())
         .collect();
+    Ok(rows)
+}
 
-    Ok(notes)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn joplin_db(bodies: &[(&str, &str)]) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE version (version INT);
+             INSERT INTO version VALUES (41);
+             CREATE TABLE notes (id TEXT, title TEXT, body TEXT, updated_time INT);",
+        )
+        .unwrap();
+        for (id, body) in bodies {
+            conn.execute(
+                "INSERT INTO notes VALUES (?1, 'Title', ?2, 1)",
+                rusqlite::params![id, body],
+            )
+            .unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn blank_bodies_are_skipped_with_and_without_the_body() {
+        let conn = joplin_db(&[
+            ("text", "Some text"),
+            ("spaces", "   "),
+            ("whitespace", " \t\r\n\n "),
+            ("padded", "\n\tText\n"),
+        ]);
+        let schema = read_schema(&conn).unwrap();
+        let scope = NoteScope::default();
+        let notes = get_all_notes(&conn, &schema, scope).unwrap();
+        let mut ids: Vec<&str> = notes.iter().map(|n| n.id.as_str()).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, ["padded", "text"]);
+
+        // The manifest of what was embedded matches what delta updates see.
+        let manifest = crate::manifest::Manifest::from_notes(schema.version, &notes);
+        let versions = get_note_versions(&conn, &schema, scope).unwrap();
+        assert!(manifest.diff(&versions).is_empty());
+    }
 }
@@ -0,0 +1,235 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use crate::index::SearchIndex;
+use crate::types::{DuplicateGroup, DuplicateNote, DuplicatePair, Note, NoteMetadata};
+
+/// Nearest neighbours checked per note. Larger sets of copies are still
+/// grouped together, because groups are joined transitively.
+const NEIGHBOURS: usize = 10;
+/// Words per shingle for the text check.
+const SHINGLE_SIZE: usize = 5;
+
+/// Every pair of indexed notes whose embeddings are at least `threshold` similar.
+/// Notes missing from `notes` (deleted or out of scope) are ignored.
+pub fn similar_pairs(
+    index: &SearchIndex,
+    notes: &HashMap<String, NoteMetadata>,
+    threshold: f32,
+) -> Vec<DuplicatePair> {
+    let mut seen: HashSet<(&str, &str)> = HashSet::new();
+    let mut pairs = Vec::new();
+
+    for (id, vector) in index.vectors() {
+        if !notes.contains_key(id) {
+            continue;
+        }
+        // +1: the note itself is its own nearest neighbour.
+        let Ok(hits) = index.search(vector, NEIGHBOURS + 1) else {
+            continue;
+        };
+        for hit in hits {
+            let Some(other) = notes.get_key_value(&hit.note_id).map(|(other, _)| other) else {
+                continue;
+            };
+            if other == id {
+                continue;
+            }
+            let key = if id < other {
+                (id.as_str(), other.as_str())
+            } else {
+                (other.as_str(), id.as_str())
+            };
+            if seen.contains(&key) {
+                continue;
+            }
+            let similarity = hit.score;
+            if similarity >= threshold {
+                seen.insert(key);
+                pairs.push(DuplicatePair {
+                    a: key.0.to_string(),
+                    b: key.1.to_string(),
+                    similarity,
+                    text_similarity: None,
+                });
+            }
+        }
+    }
+    pairs
+}
+
+/// Compare the texts of each pair and drop pairs with less than `min_similarity`
+/// shingle overlap. Pairs whose text is unavailable are dropped too.
+pub fn apply_text_check(pairs: &mut Vec<DuplicatePair>, notes: &[Note], min_similarity: f32) {
+    let wanted: HashSet<&str> = pairs.iter().flat_map(|p| [p.a.as_str(), p.b.as_str()]).collect();
+    let shingles: HashMap<&str, HashSet<u64>> = notes
+        .iter()
+        .filter(|n| wanted.contains(n.id.as_str()))
+        .map(|n| {
+            // Bodies only: copies are often retitled ("… (copy)").
+            let text = crate::preprocess::embedding_text("", &n.body, n.markup_language);
+            (n.id.as_str(), shingles(&text))
+        })
+        .collect();
+
+    pairs.retain_mut(|pair| {
+        let (Some(a), Some(b)) = (shingles.get(pair.a.as_str()), shingles.get(pair.b.as_str()))
+        else {
+            return false;
+        };
+        let union = a.union(b).count();
+        let overlap = if union == 0 { 1.0 } else { a.intersection(b).count() as f32 / union as f32 };
+        pair.text_similarity = Some(overlap);
+        overlap >= min_similarity
+    });
+}
+
+/// Hashes of every run of `SHINGLE_SIZE` consecutive words (lowercased).
+/// Texts shorter than that give a single shingle of all their words.
+fn shingles(text: &str) -> HashSet<u64> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    words
+        .windows(SHINGLE_SIZE.min(words.len().max(1)))
+        .map(|window| {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            window.hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect()
+}
+
+/// Join pairs that share a note into groups (connected components).
+/// Groups are ordered largest first, then by their best match.
+pub fn group_pairs(
+    mut pairs: Vec<DuplicatePair>,
+    notes: &HashMap<String, NoteMetadata>,
+    notebooks: &HashMap<String, String>,
+) -> Vec<DuplicateGroup> {
+    pairs.sort_by(|x, y| {
+        y.similarity
+            .partial_cmp(&x.similarity)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut parent: HashMap<&str, &str> = HashMap::new();
+    fn root<'a>(parent: &mut HashMap<&'a str, &'a str>, id: &'a str) -> &'a str {
+        let mut r = id;
+        while let Some(&p) = parent.get(r).filter(|&&p| p != r) {
+            r = p;
+        }
+        parent.insert(id, r);
+        r
+    }
+    for pair in &pairs {
+        let (ra, rb) = (root(&mut parent, &pair.a), root(&mut parent, &pair.b));
+        if ra != rb {
+            parent.insert(ra, rb);
+        }
+    }
+
+    let mut groups: HashMap<&str, DuplicateGroup> = HashMap::new();
+    for pair in &pairs {
+        let group = groups.entry(root(&mut parent, &pair.a)).or_insert_with(|| DuplicateGroup {
+            notes: vec![],
+            pairs: vec![],
+        });
+        group.pairs.push(pair.clone());
+    }
+
+    let mut groups: Vec<DuplicateGroup> = groups
+        .into_values()
+        .map(|mut group| {
+            let ids: HashSet<&str> =
+                group.pairs.iter().flat_map(|p| [p.a.as_str(), p.b.as_str()]).collect();
+            group.notes = ids
+                .into_iter()
+                .filter_map(|id| notes.get(id))
+                .map(|note| DuplicateNote {
+                    notebook: notebooks.get(&note.parent_id).cloned().unwrap_or_default(),
+                    note: note.clone(),
+                })
+                .collect();
+            group.notes.sort_by_key(|n| std::cmp::Reverse(n.note.user_updated_time));
+            group
+        })
+        .collect();
+    groups.sort_by(|x, y| {
+        y.notes.len().cmp(&x.notes.len()).then(
+            y.pairs[0]
+                .similarity
+                .partial_cmp(&x.pairs[0].similarity)
+                .unwrap_or(std::cmp::Ordering::Equal),
+        )
+    });
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(id: &str, body: &str) -> Note {
+        Note {
+            id: id.to_string(),
+            title: format!("Note {id}"),
+            body: body.to_string(),
+            markup_language: 1,
+            ..Default::default()
+        }
+    }
+
+    fn pair(a: &str, b: &str, similarity: f32) -> DuplicatePair {
+        DuplicatePair { a: a.to_string(), b: b.to_string(), similarity, text_similarity: None }
+    }
+
+    #[test]
+    fn shingle_overlap_keeps_copies_only() {
+        let text = "Meeting notes for the quarterly planning review with the whole team";
+        let notes = [
+            note("a", text),
+            note("b", &format!("{}!", text.to_uppercase())),
+            note("c", "Shopping list: milk, eggs, bread, butter and some apples for the week"),
+        ];
+        let mut pairs = vec![pair("a", "b", 0.99), pair("a", "c", 0.95), pair("a", "gone", 0.95)];
+        apply_text_check(&mut pairs, &notes, 0.5);
+
+        assert_eq!(pairs.len(), 1);
+        assert_eq!((pairs[0].a.as_str(), pairs[0].b.as_str()), ("a", "b"));
+        // Case and punctuation don't count.
+        assert_eq!(pairs[0].text_similarity, Some(1.0));
+    }
+
+    #[test]
+    fn short_texts_are_one_shingle() {
+        assert_eq!(shingles("Buy milk").len(), 1);
+        assert_eq!(shingles("one two three four five six").len(), 2);
+        assert_eq!(shingles(""), shingles("  "));
+    }
+
+    #[test]
+    fn pairs_sharing_a_note_are_merged_into_groups() {
+        let notes: HashMap<String, NoteMetadata> = ["a", "b", "c", "d", "e"]
+            .into_iter()
+            .map(|id| (id.to_string(), note(id, "").metadata()))
+            .collect();
+        let pairs = vec![pair("d", "e", 0.99), pair("a", "b", 0.9), pair("c", "b", 0.95)];
+        let groups = group_pairs(pairs, &notes, &HashMap::new());
+
+        let ids: Vec<Vec<&str>> = groups
+            .iter()
+            .map(|group| {
+                let mut ids: Vec<&str> = group.notes.iter().map(|n| n.note.id.as_str()).collect();
+                ids.sort_unstable();
+                ids
+            })
+            .collect();
+        // Largest group first, each pair in the group of its notes.
+        assert_eq!(ids, [vec!["a", "b", "c"], vec!["d", "e"]]);
+        assert_eq!(groups[0].pairs.len(), 2);
+        assert_eq!(groups[0].pairs[0].similarity, 0.95);
+    }
+}
@@ -1,49 +1,363 @@
-use std::path::Path;
-use std::sync::Mutex;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
 
 use anyhow::Result;
 use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use tokio::sync::oneshot;
+
+use crate::types::ResourceLimits;
+
+/// Identifies the model in the index manifest. Indexes built with another
+/// model are re-embedded by maintenance.
+pub const MODEL_ID: &str = "bge-small-en-v1.5";
+
+const MODEL: EmbeddingModel = EmbeddingModel::BGESmallENV15;
+
+/// Files fastembed reads for the tokenizer, next to the ONNX model file.
+const TOKENIZER_FILES: [&str; 4] = [
+    "tokenizer.json",
+    "config.json",
+    "special_tokens_map.json",
+    "tokenizer_config.json",
+];
+
+/// Default number of inference workers.
+pub const DEFAULT_WORKERS: usize = 1;
+
+/// Upper bound on inference workers. Each holds its own model session.
+pub const MAX_WORKERS: usize = 4;
+
+/// Whether `max_threads` and `low_priority` can be enforced on this platform.
+const LIMITS_SUPPORTED: bool = cfg!(target_os = "linux");
+
+/// Who is waiting for an embedding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    /// A search the user is waiting on. Served before any background work.
+    Interactive,
+    /// Indexing and alerts.
+    Background,
+}
 
 /// Wraps the fastembed TextEmbedding model (bge-small-en-v1.5, 384 dims).
 /// Model is downloaded and cached on first use (~33MB, one-time).
 ///
-/// The inner `TextEmbedding` session is protected by a `Mutex` so that
-/// concurrent calls from search queries and background delta indexing are
-/// serialized, preventing heap corruption in the ONNX Runtime C++ layer.
+/// Inference runs on dedicated worker threads, never on the async runtime.
+/// Each worker owns its own `TextEmbedding` session, so ONNX Runtime is never
+/// called concurrently on one session. Workers take interactive jobs first.
 pub struct EmbeddingPipeline {
-    model: Mutex<TextEmbedding>,
+    queue: Arc<Queue>,
+    /// Background batches are queued in slices of this many texts
+    /// (`ResourceLimits::inference_slice`), so an interactive query waits
+    /// behind at most one slice.
+    background_slice: AtomicUsize,
+}
+
+/// Which queues a worker takes jobs from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    /// Interactive jobs first, then background ones. Used without limits.
+    Any,
+    /// Interactive jobs only, on a worker that is never throttled.
+    Interactive,
+    /// Background jobs only, on a worker throttled by the resource limits.
+    Background,
+}
+
+struct Job {
+    texts: Vec<String>,
+    reply: oneshot::Sender<Result<Vec<Vec<f32>>>>,
+}
+
+#[derive(Default)]
+struct Jobs {
+    interactive: VecDeque<Job>,
+    background: VecDeque<Job>,
+    stopped: bool,
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: Mutex<Jobs>,
+    ready: Condvar,
+}
+
+impl Queue {
+    fn push(&self, job: Job, priority: Priority) -> Result<()> {
+        let mut jobs = self.jobs.lock().map_err(|e| anyhow::anyhow!("queue lock poisoned: {e}"))?;
+        match priority {
+            Priority::Interactive => jobs.interactive.push_back(job),
+            Priority::Background => jobs.background.push_back(job),
+        }
+        // Wake every worker: the one woken might not serve this queue.
+        self.ready.notify_all();
+        Ok(())
+    }
+
+    /// Block until a job for `role` is available. None once the pipeline is dropped.
+    fn pop(&self, role: Role) -> Option<Job> {
+        let mut jobs = self.jobs.lock().ok()?;
+        loop {
+            if jobs.stopped {
+                return None;
+            }
+            let job = match role {
+                Role::Any => jobs.interactive.pop_front().or_else(|| jobs.background.pop_front()),
+                Role::Interactive => jobs.interactive.pop_front(),
+                Role::Background => jobs.background.pop_front(),
+            };
+            if let Some(job) = job {
+                return Some(job);
+            }
+            jobs = self.ready.wait(jobs).ok()?;
+        }
+    }
 }
 
 impl EmbeddingPipeline {
-    /// Initialize the embedding model. Downloads on first run, cached afterwards.
+    /// Start `workers` inference threads, each loading the model. Downloads on
+    /// first run, cached afterwards. Blocks until every worker has loaded it.
+    /// With CPU limits in effect the workers only take background jobs, and one
+    /// more, unthrottled worker serves searches.
     /// `cache_dir` is the directory where the ONNX model files are stored.
     /// `show_progress` controls whether download progress is printed to stdout.
-    pub fn new(cache_dir: &Path, show_progress: bool) -> Result<Self> {
-        let model = TextEmbedding::try_new(
-            InitOptions::new(EmbeddingModel::BGESmallENV15)
-                .with_cache_dir(cache_dir.to_path_buf())
-                .with_show_download_progress(show_progress),
-        )?;
-        Ok(Self { model: Mutex::new(model) })
+    pub fn new(
+        cache_dir: &Path,
+        show_progress: bool,
+        workers: usize,
+        limits: &ResourceLimits,
+    ) -> Result<Self> {
+        let workers = workers.clamp(1, MAX_WORKERS);
+        let queue = Arc::new(Queue::default());
+        let pipeline = Self {
+            queue: queue.clone(),
+            background_slice: AtomicUsize::new(limits.inference_slice.max(1)),
+        };
+        if !limits_supported(limits) {
+            tracing::warn!("CPU thread and priority limits are only supported on Linux");
+        }
+        let roles: Vec<Role> = if throttled(limits) {
+            std::iter::once(Role::Interactive)
+                .chain(std::iter::repeat_n(Role::Background, workers))
+                .collect()
+        } else {
+            vec![Role::Any; workers]
+        };
+
+        // The first worker may download the model; the others then load it
+        // from the cache instead of downloading it in parallel.
+        for (i, role) in roles.into_iter().enumerate() {
+            let (loaded_tx, loaded_rx) = std::sync::mpsc::channel();
+            let queue = queue.clone();
+            let cache_dir = cache_dir.to_path_buf();
+            let limits = limits.clone();
+            std::thread::Builder::new()
+                .name(format!("inference-{i}"))
+                .spawn(move || {
+                    // Before loading: ONNX Runtime's threads are created with
+                    // the session and inherit these settings.
+                    if role == Role::Background {
+                        throttle_current_thread(&limits);
+                    }
+                    match load_model(cache_dir, show_progress) {
+                        Ok(model) => {
+                            let _ = loaded_tx.send(Ok(()));
+                            work(&model, &queue, role);
+                        }
+                        Err(e) => {
+                            let _ = loaded_tx.send(Err(e));
+                        }
+                    }
+                })?;
+            // Dropping `pipeline` on error stops the workers already started.
+            loaded_rx.recv()??;
+        }
+        Ok(pipeline)
+    }
+
+    /// Change the slice size of background batches queued from now on.
+    pub fn set_background_slice(&self, slice: usize) {
+        self.background_slice.store(slice.max(1), Ordering::Relaxed);
     }
 
-    /// Embed a single text string. Returns a 384-dimensional vector.
-    pub fn embed_one(&self, text: &str) -> Result<Vec<f32>> {
-        let model = self.model.lock().map_err(|e| anyhow::anyhow!("model lock poisoned: {e}"))?;
-        let mut results = model.embed(vec![text], None)?;
-        let embedding = results.remove(0);
-        Ok(normalize(embedding))
+    /// Embed a search query. Returns a 384-dimensional vector.
+    pub async fn embed_one(&self, text: &str) -> Result<Vec<f32>> {
+        let mut results = self.embed_batch(&[text], Priority::Interactive).await?;
+        results.pop().ok_or_else(|| anyhow::anyhow!("no embedding returned"))
     }
 
     /// Embed a batch of texts. More efficient than calling embed_one repeatedly.
     /// Returns one 384-dim vector per input text, in the same order.
-    pub fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+    pub async fn embed_batch(&self, texts: &[&str], priority: Priority) -> Result<Vec<Vec<f32>>> {
         if texts.is_empty() {
             return Ok(vec![]);
         }
-        let model = self.model.lock().map_err(|e| anyhow::anyhow!("model lock poisoned: {e}"))?;
-        let results = model.embed(texts.to_vec(), None)?;
-        Ok(results.into_iter().map(normalize).collect())
+        let slice = match priority {
+            Priority::Interactive => texts.len(),
+            Priority::Background => self.background_slice.load(Ordering::Relaxed),
+        };
+        // Queue every slice up front; interactive jobs still jump ahead of
+        // the ones not yet taken by a worker.
+        let mut replies = Vec::new();
+        for chunk in texts.chunks(slice) {
+            let (reply, rx) = oneshot::channel();
+            let texts = chunk.iter().map(|t| t.to_string()).collect();
+            self.queue.push(Job { texts, reply }, priority)?;
+            replies.push(rx);
+        }
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for rx in replies {
+            let slice = rx.await.map_err(|_| anyhow::anyhow!("inference worker stopped"))??;
+            embeddings.extend(slice);
+        }
+        Ok(embeddings)
+    }
+}
+
+impl Drop for EmbeddingPipeline {
+    /// Stop the workers. Jobs still queued fail with "inference worker stopped".
+    fn drop(&mut self) {
+        if let Ok(mut jobs) = self.queue.jobs.lock() {
+            jobs.stopped = true;
+            jobs.interactive.clear();
+            jobs.background.clear();
+        }
+        self.queue.ready.notify_all();
+    }
+}
+
+/// Download the model files that aren't cached yet, reporting
+/// `(downloaded, total)` bytes as they arrive. `total` grows as each file
+/// starts. fastembed can only print its download progress, so the files are
+/// fetched here first, into the cache `TextEmbedding::try_new` reads from.
+pub fn download_model(cache_dir: &Path, mut report: impl FnMut(u64, u64)) -> Result<()> {
+    let info = TextEmbedding::get_model_info(&MODEL)?;
+    // Resolve the cache and endpoint the way fastembed does.
+    let cache_dir = std::env::var("HF_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| cache_dir.to_path_buf());
+    let endpoint =
+        std::env::var("HF_ENDPOINT").unwrap_or_else(|_| "https://huggingface.co".to_string());
+
+    let cached = hf_hub::Cache::new(cache_dir.clone()).model(info.model_code.clone());
+    let missing: Vec<&str> = std::iter::once(info.model_file.as_str())
+        .chain(info.additional_files.iter().map(|f| f.as_str()))
+        .chain(TOKENIZER_FILES)
+        .filter(|file| cached.get(file).is_none())
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let repo = hf_hub::api::sync::ApiBuilder::new()
+        .with_cache_dir(cache_dir)
+        .with_endpoint(endpoint)
+        .with_progress(false)
+        .build()?
+        .model(info.model_code.clone());
+    let mut counter = ByteCounter { done: 0, total: 0, report: &mut report };
+    for file in missing {
+        repo.download_with_progress(file, FileProgress(&mut counter))?;
+    }
+    Ok(())
+}
+
+/// Bytes downloaded across all model files.
+struct ByteCounter<'a> {
+    done: u64,
+    total: u64,
+    report: &'a mut dyn FnMut(u64, u64),
+}
+
+/// hf-hub takes a progress value per file; this one adds to the shared count.
+struct FileProgress<'a, 'b>(&'a mut ByteCounter<'b>);
+
+impl hf_hub::api::Progress for FileProgress<'_, '_> {
+    fn init(&mut self, size: usize, _filename: &str) {
+        self.0.total += size as u64;
+        (self.0.report)(self.0.done, self.0.total);
+    }
+
+    fn update(&mut self, size: usize) {
+        self.0.done += size as u64;
+        (self.0.report)(self.0.done, self.0.total);
+    }
+
+    fn finish(&mut self) {}
+}
+
+fn load_model(cache_dir: PathBuf, show_progress: bool) -> Result<TextEmbedding> {
+    TextEmbedding::try_new(
+        InitOptions::new(MODEL)
+            .with_cache_dir(cache_dir)
+            .with_show_download_progress(show_progress),
+    )
+}
+
+/// Nice value of inference threads in low-priority mode.
+#[cfg(target_os = "linux")]
+const LOW_PRIORITY_NICE: libc::c_int = 10;
+
+/// Apply `limits` to the calling thread. Threads it creates inherit its CPU
+/// affinity and nice value, and fastembed sizes the ONNX Runtime thread pool
+/// from `available_parallelism`, which honours the affinity mask. Linux only.
+#[cfg(target_os = "linux")]
+fn throttle_current_thread(limits: &ResourceLimits) {
+    let size = std::mem::size_of::<libc::cpu_set_t>();
+    if let Some(max_threads) = limits.max_threads {
+        // Keep the first `max_threads` of the CPUs we may run on.
+        // SAFETY: `set` is a valid cpu_set_t of `size` bytes; pid 0 is this thread.
+        unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            if libc::sched_getaffinity(0, size, &mut set) == 0 {
+                let mut kept = 0;
+                for cpu in 0..libc::CPU_SETSIZE as usize {
+                    if libc::CPU_ISSET(cpu, &set) {
+                        if kept < max_threads.max(1) {
+                            kept += 1;
+                        } else {
+                            libc::CPU_CLR(cpu, &mut set);
+                        }
+                    }
+                }
+                if libc::sched_setaffinity(0, size, &set) != 0 {
+                    tracing::warn!("Failed to limit inference threads");
+                }
+            }
+        }
+    }
+    if limits.low_priority {
+        // Linux nice values are per thread; who = 0 is the calling thread.
+        // SAFETY: plain syscall without pointers.
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, LOW_PRIORITY_NICE) } != 0 {
+            tracing::warn!("Failed to lower inference priority");
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn throttle_current_thread(_limits: &ResourceLimits) {}
+
+/// Whether `limits` can be enforced here. Elsewhere than on Linux they can
+/// only leave `max_threads` and `low_priority` unset.
+pub fn limits_supported(limits: &ResourceLimits) -> bool {
+    LIMITS_SUPPORTED || (limits.max_threads.is_none() && !limits.low_priority)
+}
+
+/// Whether workers run throttled by `limits`.
+fn throttled(limits: &ResourceLimits) -> bool {
+    LIMITS_SUPPORTED && (limits.max_threads.is_some() || limits.low_priority)
+}
+
+/// Worker thread body: run jobs until the pipeline is dropped.
+fn work(model: &TextEmbedding, queue: &Queue, role: Role) {
+    while let Some(job) = queue.pop(role) {
+        let result = model
+            .embed(job.texts, None)
+            .map(|results| results.into_iter().map(normalize).collect());
+        let _ = job.reply.send(result);
     }
 }
 
@@ -0,0 +1,178 @@
+use std::fmt;
+
+use serde::{Serialize, Serializer};
+use tauri::{Emitter, Manager};
+
+use crate::AppMutex;
+
+/// Error returned by every Tauri command, and recorded for failures of
+/// background work. Serialized as `{ code, message, retryable }`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    /// The index hasn't been loaded or built yet.
+    IndexNotReady,
+    /// The embedding model hasn't finished loading.
+    ModelNotLoaded,
+    /// A full build or maintenance task is already running.
+    IndexBusy,
+    /// `cancel_indexing` was called with no full build running.
+    NotIndexing,
+    /// The full build was cancelled; `resume_indexing` continues it.
+    IndexingCancelled,
+    /// No Joplin database has been detected or chosen.
+    DbNotConfigured,
+    NoteNotFound,
+    RevisionNotFound,
+    SavedSearchNotFound,
+    InvalidNoteId,
+    /// `search_by_example` got neither text nor notes.
+    EmptyExample,
+    /// A saved search needs a name and a query.
+    EmptySavedSearch,
+    /// Only http(s) URLs are opened in the browser.
+    OnlyHttpAllowed,
+    /// CPU thread and priority limits can't be enforced on this platform.
+    LimitsUnsupported,
+    /// The Joplin database couldn't be opened, read or understood.
+    Database(String),
+    /// The embedding model failed to load or to embed.
+    Inference(String),
+    /// An index or data file couldn't be read or written.
+    Storage(String),
+    /// Filesystem notifications failed; the watcher fell back to polling.
+    Watcher(String),
+    /// Joplin or the browser couldn't be launched.
+    Launch(String),
+    /// A background task panicked, or something else unexpected.
+    Internal(String),
+}
+
+impl AppError {
+    pub fn database(e: impl fmt::Display) -> Self {
+        Self::Database(e.to_string())
+    }
+
+    pub fn inference(e: impl fmt::Display) -> Self {
+        Self::Inference(e.to_string())
+    }
+
+    pub fn storage(e: impl fmt::Display) -> Self {
+        Self::Storage(e.to_string())
+    }
+
+    pub fn launch(e: impl fmt::Display) -> Self {
+        Self::Launch(e.to_string())
+    }
+
+    pub fn internal(e: impl fmt::Display) -> Self {
+        Self::Internal(e.to_string())
+    }
+
+    /// Stable identifier the frontend matches on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::IndexNotReady => "index_not_ready",
+            Self::ModelNotLoaded => "model_not_loaded",
+            Self::IndexBusy => "index_busy",
+            Self::NotIndexing => "not_indexing",
+            Self::IndexingCancelled => "indexing_cancelled",
+            Self::DbNotConfigured => "db_not_configured",
+            Self::NoteNotFound => "note_not_found",
+            Self::RevisionNotFound => "revision_not_found",
+            Self::SavedSearchNotFound => "saved_search_not_found",
+            Self::InvalidNoteId => "invalid_note_id",
+            Self::EmptyExample => "empty_example",
+            Self::EmptySavedSearch => "empty_saved_search",
+            Self::OnlyHttpAllowed => "only_http_allowed",
+            Self::LimitsUnsupported => "limits_unsupported",
+            Self::Database(_) => "database_error",
+            Self::Inference(_) => "inference_error",
+            Self::Storage(_) => "storage_error",
+            Self::Watcher(_) => "watcher_error",
+            Self::Launch(_) => "launch_failed",
+            Self::Internal(_) => "internal_error",
+        }
+    }
+
+    /// Whether the same call may succeed later without the user changing
+    /// anything: the index or model is still loading, or the inference
+    /// workers were being restarted.
+    pub fn retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::IndexNotReady | Self::ModelNotLoaded | Self::IndexBusy | Self::Inference(_)
+        )
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Self::IndexNotReady => "The search index is not ready yet.",
+            Self::ModelNotLoaded => "The embedding model is still loading.",
+            Self::IndexBusy => "The index is being rebuilt or maintained.",
+            Self::NotIndexing => "No index build is running.",
+            Self::IndexingCancelled => "Indexing was cancelled.",
+            Self::DbNotConfigured => "No Joplin database is selected.",
+            Self::NoteNotFound => "The note was not found.",
+            Self::RevisionNotFound => "The note revision was not found.",
+            Self::SavedSearchNotFound => "The saved search was not found.",
+            Self::InvalidNoteId => "Invalid note ID.",
+            Self::EmptyExample => "Enter some text or pick a note to search by.",
+            Self::EmptySavedSearch => "A saved search needs a name and a query.",
+            Self::OnlyHttpAllowed => "Only http and https links can be opened.",
+            Self::LimitsUnsupported => {
+                "CPU thread and priority limits are only supported on Linux."
+            }
+            Self::Database(detail)
+            | Self::Inference(detail)
+            | Self::Storage(detail)
+            | Self::Watcher(detail)
+            | Self::Launch(detail)
+            | Self::Internal(detail) => detail,
+        };
+        f.write_str(message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Payload<'a> {
+            code: &'a str,
+            message: String,
+            retryable: bool,
+        }
+        Payload { code: self.code(), message: self.to_string(), retryable: self.retryable() }
+            .serialize(serializer)
+    }
+}
+
+/// Background failures kept for `get_error_log`. Older entries are dropped.
+const ERROR_LOG_CAPACITY: usize = 100;
+
+/// A failure of background work (indexing, delta updates, maintenance, ...),
+/// which has no caller to return it to.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorLogEntry {
+    pub time: i64, // Unix ms
+    /// The task that failed, e.g. "delta_update" or "maintenance".
+    pub source: String,
+    pub error: AppError,
+}
+
+/// Record a background failure: log it, keep it in the bounded error log
+/// and emit a `background-error` event.
+pub async fn record(app: &tauri::AppHandle, source: &str, error: AppError) {
+    tracing::warn!("{source} failed: {error}");
+    let entry = ErrorLogEntry { time: crate::usage::now_ms(), source: source.to_string(), error };
+    let _ = app.emit("background-error", &entry);
+    let state = app.state::<AppMutex>();
+    let mut s = state.lock().await;
+    if s.error_log.len() == ERROR_LOG_CAPACITY {
+        s.error_log.pop_front();
+    }
+    s.error_log.push_back(entry);
+}
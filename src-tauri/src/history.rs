@@ -0,0 +1,146 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::index::cosine_similarity;
+use crate::types::{HistoryEntry, NoteMetadata, SavedSearch, SearchOptions, SearchResult};
+
+/// Recent queries kept in the history.
+const MAX_HISTORY: usize = 50;
+/// A query typed within this long of the previous one, extending or
+/// shortening it, replaces it: search runs as the user types, and only the
+/// finished query is worth keeping.
+const TYPING_WINDOW_MS: i64 = 10_000;
+
+/// Search history and saved searches, persisted in `history.json` next to `index.bin`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchHistory {
+    /// Newest first.
+    pub recent: Vec<HistoryEntry>,
+    pub saved: Vec<SavedSearch>,
+}
+
+impl SearchHistory {
+    /// Add a query to the history, replacing an earlier identical query and
+    /// the partial query it was typed from.
+    pub fn record(&mut self, query: &str, options: &SearchOptions, now_ms: i64) {
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+        if let Some(last) = self.recent.first() {
+            let typing = now_ms - last.time < TYPING_WINDOW_MS
+                && (query.starts_with(&last.query) || last.query.starts_with(query));
+            if typing {
+                self.recent.remove(0);
+            }
+        }
+        self.recent.retain(|e| e.query != query);
+        self.recent.insert(
+            0,
+            HistoryEntry {
+                query: query.to_string(),
+                options: options.clone(),
+                time: now_ms,
+            },
+        );
+        self.recent.truncate(MAX_HISTORY);
+    }
+
+    /// Store a named search and return it.
+    pub fn save_search(
+        &mut self,
+        name: &str,
+        query: &str,
+        options: SearchOptions,
+        now_ms: i64,
+    ) -> SavedSearch {
+        // Unix ms plus a tiebreaker keeps IDs unique even for saves in the same millisecond.
+        let mut id = format!("{now_ms:x}");
+        let mut n = 0;
+        while self.saved.iter().any(|s| s.id == id) {
+            n += 1;
+            id = format!("{now_ms:x}-{n}");
+        }
+        let saved = SavedSearch {
+            id,
+            name: name.trim().to_string(),
+            query: query.to_string(),
+            options,
+            created_time: now_ms,
+            alert_threshold: None,
+            notify: false,
+        };
+        self.saved.push(saved.clone());
+        saved
+    }
+
+    /// Turn alerts for a saved search on (`threshold` set) or off. None if no
+    /// search has that ID.
+    pub fn set_alert(
+        &mut self,
+        id: &str,
+        threshold: Option<f32>,
+        notify: bool,
+    ) -> Option<SavedSearch> {
+        let saved = self.saved.iter_mut().find(|s| s.id == id)?;
+        saved.alert_threshold = threshold;
+        saved.notify = notify;
+        Some(saved.clone())
+    }
+
+    /// Remove a saved search. False if no search has that ID.
+    pub fn delete_saved(&mut self, id: &str) -> bool {
+        let before = self.saved.len();
+        self.saved.retain(|s| s.id != id);
+        self.saved.len() != before
+    }
+}
+
+/// A re-embedded note, with its vector before this pass if it had one.
+pub struct ChangedNote<'a> {
+    pub note: NoteMetadata,
+    pub vector: &'a [f32],
+    pub previous: Option<&'a [f32]>,
+}
+
+/// Changed notes that now reach the alert threshold of `search` but did not
+/// before the change, best first. Edits to a note that already matched don't
+/// alert again.
+pub fn new_matches(
+    search: &SavedSearch,
+    query_vector: &[f32],
+    changed: &[ChangedNote],
+) -> Vec<SearchResult> {
+    let Some(threshold) = search.alert_threshold else {
+        return vec![];
+    };
+    let mut matches: Vec<SearchResult> = changed
+        .iter()
+        .filter(|c| search.options.matches(&c.note))
+        .filter(|c| c.previous.is_none_or(|v| cosine_similarity(query_vector, v) < threshold))
+        .filter_map(|c| {
+            let score = cosine_similarity(query_vector, c.vector);
+            (score >= threshold).then(|| SearchResult {
+                note: c.note.clone(),
+                score,
+                revision: None,
+                ranking: None,
+                explanation: None,
+            })
+        })
+        .collect();
+    matches.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    matches
+}
+
+/// Load history from disk. Starts empty if the file is missing or unreadable.
+pub fn load(path: &Path) -> SearchHistory {
+    crate::storage::load_json(path).unwrap_or_default()
+}
+
+/// Persist history atomically (write temp file, then rename).
+pub fn save(history: &SearchHistory, path: &Path) -> Result<()> {
+    crate::storage::save_json_atomic(history, path)
+}
@@ -2,6 +2,7 @@ use anyhow::Result;
 use ruvector_core::index::hnsw::HnswIndex;
 use ruvector_core::index::VectorIndex;
 use ruvector_core::types::{DistanceMetric, HnswConfig};
+use std::collections::HashMap;
 use std::path::Path;
 
 /// Embedding dimension for bge-small-en-v1.5.
@@ -10,17 +11,32 @@ pub const DIMENSIONS: usize = 384;
 /// How many results to return from a search by default.
 pub const DEFAULT_TOP_K: usize = 25;
 
+/// Candidates fetched when metadata filters are active, so enough
+/// results survive filtering to fill a page.
+pub const FILTERED_TOP_K: usize = 250;
+
 /// A result from the search index: (note_id, similarity_score).
 /// score is in [0.0, 1.0] — higher means more semantically similar.
 pub struct IndexResult {
     pub note_id: String,
     pub score: f32,
+    /// Raw cosine distance from ruvector, before conversion and clamping.
+    pub distance: f32,
 }
 
+/// Note UUID → embedding.
+pub type Vectors = HashMap<String, Vec<f32>>;
+
 /// Wraps the ruvector-core HNSW index.
 /// Stores note UUID → embedding mappings and supports ANN search.
 pub struct SearchIndex {
     hnsw: HnswIndex,
+    /// The embedding of every note in the index. ruvector has no accessor for
+    /// the vectors it holds, and the topic map, duplicates, suggestions, MMR
+    /// and compaction need them.
+    vectors: Vectors,
+    /// Graph nodes inserted so far, including ones no longer mapped to a note.
+    nodes: usize,
 }
 
 impl SearchIndex {
@@ -35,21 +51,41 @@ impl SearchIndex {
         };
         let hnsw = HnswIndex::new(DIMENSIONS, DistanceMetric::Cosine, config)
             .map_err(|e| anyhow::anyhow!("Failed to create HNSW index: {e}"))?;
-        Ok(Self { hnsw })
+        Ok(Self { hnsw, vectors: HashMap::new(), nodes: 0 })
     }
 
-    /// Add a single note embedding to the index.
+    /// Add a single note embedding to the index, replacing any earlier one.
     pub fn add(&mut self, note_id: String, embedding: Vec<f32>) -> Result<()> {
+        self.remove(&note_id);
         self.hnsw
-            .add(note_id, embedding)
-            .map_err(|e| anyhow::anyhow!("Index add failed: {e}"))
+            .add(note_id.clone(), embedding.clone())
+            .map_err(|e| anyhow::anyhow!("Index add failed: {e}"))?;
+        self.vectors.insert(note_id, embedding);
+        self.nodes += 1;
+        Ok(())
     }
 
     /// Add many note embeddings at once (more efficient than repeated add).
+    /// Earlier embeddings of the same notes are replaced.
     pub fn add_batch(&mut self, entries: Vec<(String, Vec<f32>)>) -> Result<()> {
+        for (id, _) in &entries {
+            self.remove(id);
+        }
+        let count = entries.len();
         self.hnsw
-            .add_batch(entries)
-            .map_err(|e| anyhow::anyhow!("Index batch add failed: {e}"))
+            .add_batch(entries.clone())
+            .map_err(|e| anyhow::anyhow!("Index batch add failed: {e}"))?;
+        self.vectors.extend(entries);
+        self.nodes += count;
+        Ok(())
+    }
+
+    /// Remove a note from the index. Returns false if it wasn't indexed.
+    /// HNSW can't delete graph nodes, so the node stays but no longer maps to
+    /// the note and is never returned by `search`.
+    pub fn remove(&mut self, note_id: &str) -> bool {
+        let _ = self.hnsw.remove(&note_id.to_string());
+        self.vectors.remove(note_id).is_some()
     }
 
     /// Search for the `k` most semantically similar notes to `query_embedding`.
@@ -67,6 +103,7 @@ impl SearchIndex {
             .map(|r| IndexResult {
                 note_id: r.id,
                 score: (1.0 - r.score).clamp(0.0, 1.0),
+                distance: r.score,
             })
             .collect();
 
@@ -84,29 +121,181 @@ impl SearchIndex {
         self.hnsw.len() == 0
     }
 
+    /// Graph nodes left behind by removed or re-embedded notes. They cost
+    /// memory, search time and capacity until the index is compacted.
+    pub fn dead_nodes(&self) -> usize {
+        self.nodes.saturating_sub(self.len())
+    }
+
+    /// Graph nodes the index was sized for.
+    pub fn capacity(&self) -> usize {
+        self.hnsw.config().max_elements
+    }
+
+    /// A fresh index holding the same notes, without dead nodes. No
+    /// re-embedding is needed: the graph is rebuilt from the stored vectors.
+    pub fn compacted(&self) -> Result<Self> {
+        let mut index = Self::new((self.len() * 2).max(2000))?;
+        index.add_batch(self.vectors.iter().map(|(id, v)| (id.clone(), v.clone())).collect())?;
+        Ok(index)
+    }
+
+    /// The embedding of every note in the index.
+    pub fn vectors(&self) -> &Vectors {
+        &self.vectors
+    }
+
+    /// The embedding of one note. None if it isn't indexed.
+    pub fn vector(&self, note_id: &str) -> Option<&[f32]> {
+        self.vectors.get(note_id).map(Vec::as_slice)
+    }
+
     /// Persist the index to disk atomically (write temp file, then rename).
-    /// Prevents partial writes from corrupting the saved index.
+    /// Prevents partial writes from corrupting the saved index. The vectors
+    /// go to a second file next to it (`vectors_path`), written first.
     pub fn save(&self, path: &Path) -> Result<()> {
+        let entries: Vec<(&String, &Vec<f32>)> = self.vectors.iter().collect();
+        let vectors = bincode::encode_to_vec(&entries, bincode::config::standard())?;
+        crate::storage::write_atomic(&vectors_path(path), vectors)?;
         let bytes = self
             .hnsw
             .serialize()
             .map_err(|e| anyhow::anyhow!("Index serialize failed: {e}"))?;
-
-        // Write to a temp file alongside the target, then atomically rename.
-        let tmp_path = path.with_extension("bin.tmp");
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-        std::fs::write(&tmp_path, bytes)?;
-        std::fs::rename(&tmp_path, path)?;
-        Ok(())
+        crate::storage::write_atomic(path, bytes)
     }
 
-    /// Load a previously saved index from disk.
+    /// Load a previously saved index from disk. Fails if its vectors file is
+    /// missing or doesn't hold the same notes (e.g. a save interrupted
+    /// between the two files), so the index gets rebuilt.
     pub fn load(path: &Path) -> Result<Self> {
         let bytes = std::fs::read(path)?;
         let hnsw = HnswIndex::deserialize(&bytes)
             .map_err(|e| anyhow::anyhow!("Index deserialize failed: {e}"))?;
-        Ok(Self { hnsw })
+        let bytes = std::fs::read(vectors_path(path))?;
+        let (entries, _): (Vec<(String, Vec<f32>)>, _) =
+            bincode::decode_from_slice(&bytes, bincode::config::standard())?;
+        let vectors: Vectors = entries.into_iter().collect();
+        if vectors.len() != hnsw.len() {
+            let (saved, indexed) = (vectors.len(), hnsw.len());
+            anyhow::bail!("Index vectors don't match the index ({saved} ≠ {indexed})");
+        }
+        // Only live notes are inserted into the rebuilt graph.
+        let nodes = hnsw.len();
+        Ok(Self { hnsw, vectors, nodes })
+    }
+}
+
+/// Delete the index saved at `path` and its vectors file, if present.
+pub fn delete_saved(path: &Path) {
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(vectors_path(path));
+}
+
+/// File holding the vectors of the index saved at `path`
+/// (`index.bin` → `index.vectors`).
+pub fn vectors_path(path: &Path) -> std::path::PathBuf {
+    path.with_extension("vectors")
+}
+
+/// Cosine similarity of two embeddings.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denom = norm(a) * norm(b);
+    if denom == 0.0 { 0.0 } else { dot / denom }
+}
+
+/// Scale `v` to unit length in place (no-op for the zero vector).
+pub fn normalize(v: &mut [f32]) {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vector(seed: usize) -> Vec<f32> {
+        let mut v: Vec<f32> =
+            (0..DIMENSIONS).map(|i| ((i * 7 + seed * 13) % 31) as f32 + 1.0).collect();
+        normalize(&mut v);
+        v
+    }
+
+    fn id(n: usize) -> String {
+        format!("{n:032x}")
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir()
+            .join(format!("jss-index-test-{}-{name}", std::process::id()))
+            .join("index.bin")
+    }
+
+    #[test]
+    fn vectors_follow_adds_replacements_and_removals() {
+        let mut index = SearchIndex::new(100).unwrap();
+        index.add_batch((0..5).map(|n| (id(n), vector(n))).collect()).unwrap();
+        index.add(id(2), vector(9)).unwrap();
+        assert!(index.remove(&id(4)));
+        assert!(!index.remove(&id(4)));
+
+        assert_eq!(index.vectors().len(), 4);
+        assert_eq!(index.vector(&id(0)), Some(vector(0).as_slice()));
+        assert_eq!(index.vector(&id(2)), Some(vector(9).as_slice()));
+        assert_eq!(index.vector(&id(4)), None);
+        assert_eq!(index.dead_nodes(), 2);
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = temp_path("round-trip");
+        let mut index = SearchIndex::new(100).unwrap();
+        index.add_batch((0..5).map(|n| (id(n), vector(n))).collect()).unwrap();
+        index.remove(&id(1));
+        index.save(&path).unwrap();
+
+        let loaded = SearchIndex::load(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(loaded.len(), 4);
+        assert_eq!(loaded.dead_nodes(), 0);
+        assert_eq!(loaded.capacity(), 100);
+        assert_eq!(loaded.vectors(), index.vectors());
+        let hits = loaded.search(&vector(3), 1).unwrap();
+        assert_eq!(hits[0].note_id, id(3));
+    }
+
+    #[test]
+    fn load_fails_without_matching_vectors() {
+        let path = temp_path("mismatch");
+        let mut index = SearchIndex::new(100).unwrap();
+        index.add_batch((0..3).map(|n| (id(n), vector(n))).collect()).unwrap();
+        index.save(&path).unwrap();
+
+        // Vectors of a different save.
+        let mut other = SearchIndex::new(100).unwrap();
+        other.add(id(0), vector(0)).unwrap();
+        let other_path = path.with_file_name("other.bin");
+        other.save(&other_path).unwrap();
+        std::fs::rename(vectors_path(&other_path), vectors_path(&path)).unwrap();
+        assert!(SearchIndex::load(&path).is_err());
+
+        std::fs::remove_file(vectors_path(&path)).unwrap();
+        assert!(SearchIndex::load(&path).is_err());
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn compaction_keeps_live_vectors_only() {
+        let mut index = SearchIndex::new(100).unwrap();
+        index.add_batch((0..5).map(|n| (id(n), vector(n))).collect()).unwrap();
+        index.add(id(0), vector(7)).unwrap();
+        index.remove(&id(3));
+
+        let compacted = index.compacted().unwrap();
+        assert_eq!(compacted.dead_nodes(), 0);
+        assert_eq!(compacted.vectors(), index.vectors());
     }
 }
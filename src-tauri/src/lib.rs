@@ -1,25 +1,46 @@
 pub mod commands;
 pub mod db;
+pub mod duplicates;
 pub mod embeddings;
+pub mod error;
+pub mod history;
 pub mod index;
+pub mod links;
+pub mod maintenance;
+pub mod manifest;
+pub mod preprocess;
+pub mod revisions;
+pub mod search;
+pub mod settings;
+pub mod storage;
+pub mod suggest;
+pub mod topics;
 pub mod types;
+pub mod usage;
 pub mod watcher;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
 use tauri::Manager;
 
 use crate::embeddings::EmbeddingPipeline;
+use crate::error::ErrorLogEntry;
+use crate::history::SearchHistory;
 use crate::index::SearchIndex;
-use crate::types::{IndexStatus, NoteMetadata};
+use crate::links::LinkGraph;
+use crate::manifest::Manifest;
+use crate::types::{
+    IndexStatus, MaintenanceStatus, NoteMetadata, RevisionMetadata, Settings, WatcherStatus,
+};
+use crate::usage::UsageData;
 
 /// All runtime state shared across Tauri commands.
 pub struct AppState {
     /// Path to the Joplin SQLite database.
     pub db_path: Option<String>,
-    /// Loaded embedding model (all-MiniLM-L6-v2).
+    /// Handle to the inference workers running the embedding model.
     /// Wrapped in Arc so it can be cloned out of the mutex for lock-free inference.
     pub embedding_pipeline: Option<Arc<EmbeddingPipeline>>,
     /// HNSW vector index.
@@ -29,25 +50,56 @@ pub struct AppState {
     /// In-memory note metadata cache for fast lookup after search.
     /// Maps note UUID → NoteMetadata (no body to keep RAM usage low).
     pub note_cache: HashMap<String, NoteMetadata>,
-    /// Updated_time of the most-recently indexed note (Unix ms).
-    /// Used by the file watcher for delta queries.
-    pub last_scan_timestamp: i64,
+    /// Internal `:/id` links between notes, rebuilt with the note cache.
+    pub link_graph: LinkGraph,
+    /// Version and content hash of every note in `search_index`.
+    /// Delta updates diff it against the database to find changes.
+    pub manifest: Manifest,
     /// IDs of notes soft-deleted since the last full rebuild.
     /// Search results are filtered against this set so deleted notes
     /// disappear immediately without waiting for the next full rebuild.
     pub deleted_note_ids: HashSet<String>,
-    /// Wall-clock instant of the last full index rebuild.
-    /// Used to schedule the periodic background rebuild (every 5 minutes).
-    pub last_full_rebuild_ts: std::time::Instant,
+    /// Last search or note change. Maintenance waits until the app has been
+    /// idle for a while.
+    pub last_activity: std::time::Instant,
     /// Status reported to the frontend.
     pub index_status: IndexStatus,
     /// True while a full index build is running. Prevents concurrent rebuilds.
     pub is_indexing: bool,
+    /// Set by `cancel_indexing`; the running full build stops after its
+    /// current batch, leaving a checkpoint to resume from.
+    pub cancel_indexing: bool,
     /// True while the embedding model is being loaded. Prevents duplicate downloads.
     pub is_pipeline_loading: bool,
     /// True while a delta update is running. Prevents overlapping delta passes
     /// from double-inserting embeddings into the HNSW index.
     pub is_delta_updating: bool,
+    /// User settings, loaded from `settings.json` on startup.
+    pub settings: Settings,
+    /// Separate HNSW index over reconstructed note revisions.
+    /// Only populated when `settings.index_revisions` is on.
+    pub revision_index: Option<Arc<tokio::sync::RwLock<SearchIndex>>>,
+    /// Maps revision UUID → RevisionMetadata for every revision in `revision_index`.
+    pub revision_cache: HashMap<String, RevisionMetadata>,
+    /// (row count, newest updated_time) of the revisions table at the last
+    /// revision indexing pass. Unchanged fingerprint → nothing to do.
+    pub revision_fingerprint: (i64, i64),
+    /// True while revisions are being indexed. Prevents overlapping passes.
+    pub is_revision_indexing: bool,
+    /// Note opens and pins, loaded from `usage.json` on startup.
+    pub usage: UsageData,
+    /// Recent queries and saved searches, loaded from `history.json` on startup.
+    pub history: SearchHistory,
+    /// True once the file watcher task is running. It is never stopped.
+    pub is_watching: bool,
+    /// Wakes the file watcher so it re-reads the DB path and watcher settings.
+    pub watcher_wakeup: Arc<tokio::sync::Notify>,
+    /// Watcher state reported to the frontend.
+    pub watcher_status: WatcherStatus,
+    /// Index maintenance state reported to the frontend.
+    pub maintenance_status: MaintenanceStatus,
+    /// Recent failures of background work, oldest first. Bounded.
+    pub error_log: VecDeque<ErrorLogEntry>,
 }
 
 impl Default for AppState {
@@ -57,20 +109,27 @@ impl Default for AppState {
             embedding_pipeline: None,
             search_index: None,
             note_cache: HashMap::new(),
-            last_scan_timestamp: 0,
+            link_graph: LinkGraph::default(),
+            manifest: Manifest::default(),
             deleted_note_ids: HashSet::new(),
-            last_full_rebuild_ts: std::time::Instant::now(),
-            index_status: IndexStatus {
-                total_notes: 0,
-                indexed_notes: 0,
-                is_ready: false,
-                is_downloading_model: false,
-                download_progress: 0.0,
-                error: None,
-            },
+            last_activity: std::time::Instant::now(),
+            index_status: IndexStatus::default(),
             is_indexing: false,
+            cancel_indexing: false,
             is_pipeline_loading: false,
             is_delta_updating: false,
+            settings: Settings::default(),
+            revision_index: None,
+            revision_cache: HashMap::new(),
+            revision_fingerprint: (0, 0),
+            is_revision_indexing: false,
+            usage: UsageData::default(),
+            history: SearchHistory::default(),
+            is_watching: false,
+            watcher_wakeup: Arc::new(tokio::sync::Notify::new()),
+            watcher_status: WatcherStatus::default(),
+            maintenance_status: MaintenanceStatus::default(),
+            error_log: VecDeque::new(),
         }
     }
 }
@@ -174,16 +233,43 @@ pub fn run() {
     tracing_subscriber::fmt().with_max_level(tracing::Level::WARN).init();
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
         .manage(AppMutex::new(AppState::default()))
         .invoke_handler(tauri::generate_handler![
             commands::detect_db_path,
             commands::set_joplin_db_path,
             commands::search_notes,
+            commands::search_by_example,
             commands::get_index_status,
             commands::get_note,
             commands::trigger_reindex,
+            commands::cancel_indexing,
+            commands::resume_indexing,
+            commands::set_resource_limits,
             commands::open_in_joplin,
             commands::open_external_url,
+            commands::get_settings,
+            commands::update_settings,
+            commands::get_note_revision,
+            commands::get_backlinks,
+            commands::get_outgoing_links,
+            commands::get_topic_map,
+            commands::find_duplicates,
+            commands::suggest_tags_and_notebook,
+            commands::pin_note,
+            commands::get_pinned_notes,
+            commands::get_watcher_status,
+            commands::get_maintenance_status,
+            commands::run_maintenance,
+            commands::get_search_history,
+            commands::clear_search_history,
+            commands::list_saved_searches,
+            commands::save_search,
+            commands::set_saved_search_alert,
+            commands::delete_saved_search,
+            commands::run_saved_search,
+            commands::get_error_log,
+            commands::clear_error_log,
         ])
         .setup(|app| {
             // Set the window icon explicitly so the taskbar shows our icon on Linux.
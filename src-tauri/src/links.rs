@@ -0,0 +1,136 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::types::Note;
+
+/// Directed graph of Joplin internal links (`[title](:/<id>)`) between notes.
+/// Targets are kept as written, so links to resources (attachments) are
+/// recorded too; callers drop anything that isn't a known note.
+#[derive(Clone, Default)]
+pub struct LinkGraph {
+    outgoing: HashMap<String, HashSet<String>>,
+    incoming: HashMap<String, HashSet<String>>,
+}
+
+impl LinkGraph {
+    pub fn from_notes(notes: &[Note]) -> Self {
+        let mut graph = Self::default();
+        for note in notes {
+            graph.set_links(&note.id, &note.body);
+        }
+        graph
+    }
+
+    /// Replace the outgoing links of `note_id` with those found in `body`.
+    pub fn set_links(&mut self, note_id: &str, body: &str) {
+        self.remove_note(note_id);
+        let targets: HashSet<String> = extract_links(body)
+            .into_iter()
+            .filter(|t| t != note_id)
+            .collect();
+        if targets.is_empty() {
+            return;
+        }
+        for target in &targets {
+            self.incoming
+                .entry(target.clone())
+                .or_default()
+                .insert(note_id.to_string());
+        }
+        self.outgoing.insert(note_id.to_string(), targets);
+    }
+
+    /// Drop the outgoing links of `note_id`. Links pointing at it are kept so
+    /// they reappear if the note is restored.
+    pub fn remove_note(&mut self, note_id: &str) {
+        let Some(targets) = self.outgoing.remove(note_id) else {
+            return;
+        };
+        for target in targets {
+            if let Some(sources) = self.incoming.get_mut(&target) {
+                sources.remove(note_id);
+                if sources.is_empty() {
+                    self.incoming.remove(&target);
+                }
+            }
+        }
+    }
+
+    /// IDs of notes that link to `note_id`.
+    pub fn backlinks(&self, note_id: &str) -> impl Iterator<Item = &String> {
+        self.incoming.get(note_id).into_iter().flatten()
+    }
+
+    /// IDs that `note_id` links to.
+    pub fn outgoing(&self, note_id: &str) -> impl Iterator<Item = &String> {
+        self.outgoing.get(note_id).into_iter().flatten()
+    }
+}
+
+/// IDs referenced as `:/<32-hex-id>` in a note body. This covers Markdown
+/// links and images as well as `href`/`src` attributes in HTML notes.
+pub fn extract_links(body: &str) -> Vec<String> {
+    let bytes = body.as_bytes();
+    let mut ids = Vec::new();
+    let mut from = 0;
+    while let Some(pos) = body[from..].find(":/") {
+        let start = from + pos + 2;
+        let end = start + 32;
+        let is_id = bytes
+            .get(start..end)
+            .is_some_and(|id| id.iter().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')))
+            // A longer hex run is something else (e.g. a hash in a URL).
+            && !bytes.get(end).is_some_and(|b| b.is_ascii_hexdigit());
+        if is_id {
+            ids.push(body[start..end].to_string());
+            from = end;
+        } else {
+            from = start;
+        }
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: &str = "0123456789abcdef0123456789abcdef";
+    const B: &str = "fedcba9876543210fedcba9876543210";
+
+    #[test]
+    fn ids_are_extracted_from_markdown_and_html() {
+        let body = format!("See [a](:/{A}) and ![scan](:/{B}).\n<a href=\":/{A}\">again</a>");
+        assert_eq!(extract_links(&body), [A, B, A]);
+    }
+
+    #[test]
+    fn only_whole_lowercase_ids_count() {
+        let upper = A.to_uppercase();
+        let body = format!(
+            "https://example.com/x :/{A}0 :/{upper} :/{short} done",
+            short = &A[..31]
+        );
+        assert!(extract_links(&body).is_empty());
+        // Followed by something that isn't hex.
+        assert_eq!(extract_links(&format!("[a](:/{A}#heading)")), [A]);
+    }
+
+    #[test]
+    fn backlinks_follow_edits_and_skip_self_links() {
+        let mut graph = LinkGraph::default();
+        graph.set_links("n1", &format!("[a](:/{A}) [b](:/{B})"));
+        graph.set_links(B, &format!("[a](:/{A}) [me](:/{B})"));
+        let mut sources: Vec<&String> = graph.backlinks(A).collect();
+        sources.sort();
+        assert_eq!(sources, [B, "n1"]);
+        assert_eq!(graph.outgoing(B).collect::<Vec<_>>(), [A]);
+
+        graph.set_links("n1", &format!("only [b](:/{B}) now"));
+        assert_eq!(graph.backlinks(A).collect::<Vec<_>>(), [B]);
+
+        // Links to a removed note stay, for when it's restored.
+        graph.remove_note(B);
+        assert!(graph.backlinks(A).next().is_none());
+        assert_eq!(graph.backlinks(B).collect::<Vec<_>>(), ["n1"]);
+    }
+}
@@ -0,0 +1,315 @@
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+use crate::commands::{index_file_path, manifest_file_path};
+use crate::error::AppError;
+use crate::types::{MaintenanceSettings, MaintenanceStatus};
+use crate::AppMutex;
+
+/// How often the scheduler checks the index health.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Compact before the graph fills up the capacity it was built with.
+const MAX_CAPACITY_SHARE: f32 = 0.9;
+
+/// Index upkeep, cheapest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Task {
+    /// Rebuild the HNSW graph from the stored vectors, dropping dead nodes.
+    Compact,
+    /// Re-embed every note into the current index after the Joplin schema
+    /// changed: the text read for a note may differ even where the manifest's
+    /// content hash (title, body, markup) doesn't.
+    Reembed,
+    /// Re-embed everything: the index was built with another model.
+    Rebuild,
+}
+
+impl Task {
+    pub fn name(self) -> &'static str {
+        match self {
+            Task::Compact => "compact",
+            Task::Reembed => "reembed",
+            Task::Rebuild => "rebuild",
+        }
+    }
+}
+
+/// Measurements the scheduler decides on.
+pub struct IndexHealth {
+    pub live_nodes: usize,
+    pub dead_nodes: usize,
+    pub capacity: usize,
+    /// `embeddings::MODEL_ID` recorded in the manifest ("" if unknown).
+    pub model: String,
+    pub manifest_schema: i64,
+    pub db_schema: i64,
+}
+
+/// The most thorough task the index needs, if any. A rebuild or re-embed
+/// doesn't also need a compaction: it leaves fewer dead nodes than it adds.
+pub fn needed_task(health: &IndexHealth, settings: &MaintenanceSettings) -> Option<Task> {
+    if !health.model.is_empty() && health.model != crate::embeddings::MODEL_ID {
+        return Some(Task::Rebuild);
+    }
+    // 0: not recorded, so nothing says the schema changed.
+    if health.manifest_schema != 0 && health.manifest_schema != health.db_schema {
+        return Some(Task::Reembed);
+    }
+    let nodes = health.live_nodes + health.dead_nodes;
+    let dead_ratio = if nodes == 0 { 0.0 } else { health.dead_nodes as f32 / nodes as f32 };
+    if dead_ratio >= settings.compact_dead_ratio
+        || nodes as f32 >= health.capacity as f32 * MAX_CAPACITY_SHARE
+    {
+        return Some(Task::Compact);
+    }
+    None
+}
+
+/// Start the background scheduler. Every `CHECK_INTERVAL` it checks the
+/// index health and runs the needed task once the app has been idle for
+/// `MaintenanceSettings::idle_minutes` and the machine isn't busy.
+pub fn start_scheduler(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            match run(&app, false).await {
+                // Nothing to check yet, or already being worked on.
+                Ok(_) | Err(AppError::IndexBusy | AppError::IndexNotReady) => {}
+                Err(e) => crate::error::record(&app, "maintenance", e).await,
+            }
+        }
+    });
+}
+
+/// Check the index and run the task it needs. With `force`, run it now even
+/// if maintenance is paused or the app is in use. Returns the task that ran.
+pub async fn run(app: &tauri::AppHandle, force: bool) -> Result<Option<Task>, AppError> {
+    let (settings, idle_for, index_arc, db_path, model, manifest_schema) = {
+        let state = app.state::<AppMutex>();
+        let s = state.lock().await;
+        if s.is_indexing || s.maintenance_status.running.is_some() {
+            return Err(AppError::IndexBusy);
+        }
+        (
+            s.settings.maintenance.clone(),
+            s.last_activity.elapsed(),
+            s.search_index.clone(),
+            s.db_path.clone(),
+            s.manifest.model.clone(),
+            s.manifest.schema_version,
+        )
+    };
+    let (Some(index_arc), Some(db_path)) = (index_arc, db_path) else {
+        return Err(AppError::IndexNotReady);
+    };
+    let db_schema = crate::db::open_joplin_db(&db_path)
+        .and_then(|conn| crate::db::read_schema(&conn))
+        .map_err(AppError::database)?
+        .version;
+
+    let health = {
+        let index = index_arc.read().await;
+        IndexHealth {
+            live_nodes: index.len(),
+            dead_nodes: index.dead_nodes(),
+            capacity: index.capacity(),
+            model,
+            manifest_schema,
+            db_schema,
+        }
+    };
+    let task = needed_task(&health, &settings);
+    update_status(app, |status| {
+        status.needed = task.map(|t| t.name().to_string());
+        status.live_nodes = health.live_nodes;
+        status.dead_nodes = health.dead_nodes;
+    })
+    .await;
+
+    let Some(task) = task else {
+        return Ok(None);
+    };
+    if !force {
+        let idle = idle_for >= Duration::from_secs(settings.idle_minutes as u64 * 60);
+        if settings.paused || !idle || system_busy() {
+            return Ok(None);
+        }
+    }
+
+    update_status(app, |status| status.running = Some(task.name().to_string())).await;
+    tracing::info!("Index maintenance: {}", task.name());
+    let result = match task {
+        Task::Compact => compact(app, db_path).await,
+        Task::Reembed => reembed(app, db_path).await,
+        Task::Rebuild => crate::commands::rebuild_index(app.clone()).await,
+    };
+    update_status(app, |status| {
+        status.running = None;
+        if result.is_ok() {
+            status.needed = None;
+            status.last_task = Some(task.name().to_string());
+            status.last_run = Some(crate::usage::now_ms());
+        }
+    })
+    .await;
+    result.map(|_| Some(task))
+}
+
+/// Swap in a compacted copy of the index. Delta updates are held off while
+/// it is built (by claiming their guard), then one runs to catch up.
+async fn compact(app: &tauri::AppHandle, db_path: String) -> Result<(), AppError> {
+    let index_arc = {
+        let state = app.state::<AppMutex>();
+        let mut s = state.lock().await;
+        if s.is_indexing || s.is_delta_updating {
+            return Err(AppError::IndexBusy);
+        }
+        s.is_delta_updating = true;
+        s.search_index.clone()
+    };
+    let mut result = Ok(());
+    if let Some(arc) = index_arc {
+        let guard = arc.read_owned().await;
+        result = match tokio::task::spawn_blocking(move || guard.compacted()).await {
+            Ok(Ok(index)) => {
+                let compacted = std::sync::Arc::new(tokio::sync::RwLock::new(index));
+                let swapped = {
+                    let state = app.state::<AppMutex>();
+                    let mut s = state.lock().await;
+                    // A full build started meanwhile will replace the index anyway.
+                    if !s.is_indexing {
+                        s.maintenance_status.dead_nodes = 0;
+                        s.search_index = Some(compacted.clone());
+                    }
+                    !s.is_indexing
+                };
+                // Saved outside the app lock, which would block every command.
+                if swapped {
+                    let index = compacted.read_owned().await;
+                    let path = index_file_path(app);
+                    tokio::task::spawn_blocking(move || index.save(&path))
+                        .await
+                        .map_err(AppError::internal)
+                        .and_then(|saved| saved.map_err(AppError::storage))
+                } else {
+                    Ok(())
+                }
+            }
+            Ok(Err(e)) => Err(AppError::internal(e)),
+            Err(e) => Err(AppError::internal(e)),
+        };
+    }
+    {
+        let state = app.state::<AppMutex>();
+        state.lock().await.is_delta_updating = false;
+    }
+    crate::commands::run_delta_update(app.clone(), db_path).await;
+    result
+}
+
+/// Mark every note in the manifest stale, so a delta update re-embeds them
+/// all into the current index, which keeps serving searches meanwhile.
+async fn reembed(app: &tauri::AppHandle, db_path: String) -> Result<(), AppError> {
+    let schema = crate::db::open_joplin_db(&db_path)
+        .and_then(|conn| crate::db::read_schema(&conn))
+        .map_err(AppError::database)?;
+    let manifest = {
+        let state = app.state::<AppMutex>();
+        let mut s = state.lock().await;
+        s.manifest.mark_stale();
+        s.manifest.schema_version = schema.version;
+        s.manifest.clone()
+    };
+    // Saved before the delta pass, so notes it doesn't get to are still
+    // re-embedded by the next one, after a restart too.
+    crate::manifest::save(&manifest, &manifest_file_path(app)).map_err(AppError::storage)?;
+    crate::commands::run_delta_update(app.clone(), db_path).await;
+    Ok(())
+}
+
+/// Whether the machine is too busy for background work. Uses the 1-minute
+/// load average where available (Linux); elsewhere only app idleness counts.
+fn system_busy() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        let cpus = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        let load = std::fs::read_to_string("/proc/loadavg")
+            .ok()
+            .and_then(|s| s.split_whitespace().next()?.parse::<f32>().ok());
+        if let Some(load) = load {
+            return load > cpus as f32 * 0.75;
+        }
+    }
+    false
+}
+
+/// Change the maintenance status and emit a `maintenance-status` event.
+async fn update_status(app: &tauri::AppHandle, change: impl FnOnce(&mut MaintenanceStatus)) {
+    let state = app.state::<AppMutex>();
+    let mut s = state.lock().await;
+    change(&mut s.maintenance_status);
+    let _ = app.emit("maintenance-status", &s.maintenance_status);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn healthy() -> IndexHealth {
+        IndexHealth {
+            live_nodes: 100,
+            dead_nodes: 0,
+            capacity: 2000,
+            model: crate::embeddings::MODEL_ID.to_string(),
+            manifest_schema: 41,
+            db_schema: 41,
+        }
+    }
+
+    #[test]
+    fn healthy_index_needs_nothing() {
+        assert_eq!(needed_task(&healthy(), &MaintenanceSettings::default()), None);
+    }
+
+    #[test]
+    fn another_model_needs_a_rebuild_before_anything_else() {
+        let health = IndexHealth {
+            model: "other-model".to_string(),
+            db_schema: 42,
+            dead_nodes: 100,
+            ..healthy()
+        };
+        assert_eq!(needed_task(&health, &MaintenanceSettings::default()), Some(Task::Rebuild));
+        // No model recorded: assumed current.
+        let health = IndexHealth { model: String::new(), ..healthy() };
+        assert_eq!(needed_task(&health, &MaintenanceSettings::default()), None);
+    }
+
+    #[test]
+    fn schema_change_needs_a_reembed_before_compaction() {
+        let health = IndexHealth { db_schema: 42, dead_nodes: 100, ..healthy() };
+        assert_eq!(needed_task(&health, &MaintenanceSettings::default()), Some(Task::Reembed));
+        // No schema recorded: nothing says it changed.
+        let health = IndexHealth { manifest_schema: 0, db_schema: 42, ..healthy() };
+        assert_eq!(needed_task(&health, &MaintenanceSettings::default()), None);
+    }
+
+    #[test]
+    fn dead_nodes_at_the_threshold_need_compaction() {
+        let settings = MaintenanceSettings { compact_dead_ratio: 0.25, ..Default::default() };
+        let below = IndexHealth { live_nodes: 76, dead_nodes: 24, ..healthy() };
+        assert_eq!(needed_task(&below, &settings), None);
+        let at = IndexHealth { live_nodes: 75, dead_nodes: 25, ..healthy() };
+        assert_eq!(needed_task(&at, &settings), Some(Task::Compact));
+    }
+
+    #[test]
+    fn nearly_full_graph_needs_compaction() {
+        let settings = MaintenanceSettings::default();
+        let below = IndexHealth { live_nodes: 1799, capacity: 2000, ..healthy() };
+        assert_eq!(needed_task(&below, &settings), None);
+        let full = IndexHealth { live_nodes: 1800, capacity: 2000, ..healthy() };
+        assert_eq!(needed_task(&full, &settings), Some(Task::Compact));
+    }
+}
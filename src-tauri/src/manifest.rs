@@ -0,0 +1,199 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::db::NoteVersion;
+use crate::types::Note;
+
+/// The version and content hash of a note as it was embedded.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub updated_time: i64,
+    pub in_trash: bool,
+    /// `content_hash` of the embedded text.
+    pub hash: u64,
+    /// Embedded from text that may no longer match the hash (read under an
+    /// older Joplin schema), so the note is re-embedded on the next pass.
+    #[serde(default)]
+    pub stale: bool,
+}
+
+/// What the note index holds, persisted in `manifest.json` next to `index.bin`.
+/// Delta updates diff it against the database, so changes are found however
+/// their timestamps compare (clock skew, restores with old timestamps).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Manifest {
+    /// `embeddings::MODEL_ID` of the model the notes were embedded with.
+    /// Empty for manifests written before it was recorded.
+    pub model: String,
+    /// Joplin schema version of the database when the index was built.
+    /// 0 for manifests written before it was recorded.
+    pub schema_version: i64,
+    /// Note UUID → entry.
+    pub notes: HashMap<String, ManifestEntry>,
+}
+
+/// Notes that differ between the manifest and the database.
+#[derive(Debug, Default)]
+pub struct ManifestDiff {
+    /// In the database but not the index.
+    pub added: Vec<String>,
+    /// In both, with a different version. Their text may still be unchanged.
+    pub changed: Vec<String>,
+    /// In the index but no longer in the database (or out of scope).
+    pub removed: Vec<String>,
+}
+
+impl ManifestDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl Manifest {
+    /// Manifest for notes embedded with the current model from a database
+    /// with `schema_version`.
+    pub fn new(schema_version: i64) -> Self {
+        Self {
+            model: crate::embeddings::MODEL_ID.to_string(),
+            schema_version,
+            notes: HashMap::new(),
+        }
+    }
+
+    /// Take `schema_version` as the schema the index was built with if none
+    /// was recorded: the index is assumed current rather than re-embedded.
+    pub fn adopt_schema(&mut self, schema_version: i64) {
+        if self.schema_version == 0 {
+            self.schema_version = schema_version;
+        }
+    }
+
+    pub fn from_notes(schema_version: i64, notes: &[Note]) -> Self {
+        let mut manifest = Self::new(schema_version);
+        for note in notes {
+            manifest.insert(note);
+        }
+        manifest
+    }
+
+    /// Record that `note` was embedded in its current state.
+    pub fn insert(&mut self, note: &Note) {
+        self.notes.insert(
+            note.id.clone(),
+            ManifestEntry {
+                updated_time: note.updated_time,
+                in_trash: note.in_trash,
+                hash: content_hash(note),
+                stale: false,
+            },
+        );
+    }
+
+    /// Whether `note` has the same embedded text as when it was recorded.
+    pub fn same_content(&self, note: &Note) -> bool {
+        self.notes.get(&note.id).is_some_and(|e| !e.stale && e.hash == content_hash(note))
+    }
+
+    /// Have every note re-embedded by the next delta update. The entries are
+    /// kept, so notes deleted meanwhile are still removed from the index.
+    pub fn mark_stale(&mut self) {
+        for entry in self.notes.values_mut() {
+            entry.stale = true;
+        }
+    }
+
+    /// Set difference between the manifest and the current database versions.
+    pub fn diff(&self, current: &HashMap<String, NoteVersion>) -> ManifestDiff {
+        let mut diff = ManifestDiff::default();
+        for (id, version) in current {
+            match self.notes.get(id) {
+                None => diff.added.push(id.clone()),
+                Some(e)
+                    if e.stale
+                        || e.updated_time != version.updated_time
+                        || e.in_trash != version.in_trash =>
+                {
+                    diff.changed.push(id.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed = self
+            .notes
+            .keys()
+            .filter(|id| !current.contains_key(*id))
+            .cloned()
+            .collect();
+        diff
+    }
+}
+
+/// Stable hash (64-bit FNV-1a) of the text a note is embedded from.
+/// Persisted, so it must not depend on the Rust version like `DefaultHasher`.
+pub fn content_hash(note: &Note) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let markup = note.markup_language.to_le_bytes();
+    for bytes in [note.title.as_bytes(), &[0], note.body.as_bytes(), &[0], &markup] {
+        for &b in bytes {
+            hash ^= b as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// Load the manifest from disk. None if it is missing or unreadable.
+pub fn load(path: &Path) -> Option<Manifest> {
+    crate::storage::load_json(path)
+}
+
+/// Persist the manifest atomically (write temp file, then rename).
+pub fn save(manifest: &Manifest, path: &Path) -> Result<()> {
+    crate::storage::save_json_atomic(manifest, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(id: &str, updated_time: i64, body: &str) -> Note {
+        Note {
+            id: id.to_string(),
+            title: "Title".to_string(),
+            body: body.to_string(),
+            updated_time,
+            markup_language: 1,
+            ..Default::default()
+        }
+    }
+
+    fn versions(notes: &[&Note]) -> HashMap<String, NoteVersion> {
+        notes
+            .iter()
+            .map(|n| {
+                let version =
+                    NoteVersion { updated_time: n.updated_time, in_trash: n.in_trash };
+                (n.id.clone(), version)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stale_notes_are_changed_but_still_removable() {
+        let (a, b) = (note("a", 1, "one"), note("b", 1, "two"));
+        let mut manifest = Manifest::from_notes(41, &[a.clone(), b.clone()]);
+        assert!(manifest.diff(&versions(&[&a, &b])).is_empty());
+
+        manifest.mark_stale();
+        assert!(!manifest.same_content(&a));
+        let diff = manifest.diff(&versions(&[&a]));
+        assert_eq!(diff.changed, vec!["a".to_string()]);
+        assert_eq!(diff.removed, vec!["b".to_string()]);
+
+        manifest.insert(&a);
+        assert!(manifest.same_content(&a));
+    }
+}
@@ -0,0 +1,604 @@
+//! Turns note Markdown/HTML into plain text before embedding.
+//!
+//! The model has a small token budget, so markup that carries no meaning —
+//! base64 images, clipped HTML, `:/resourceid` links, long URLs, table
+//! pipes — is dropped, while headings, list text, link text, image alt text
+//! and code identifiers are kept.
+
+/// Joplin's `markup_language` values.
+pub const MARKUP_MARKDOWN: i64 = 1;
+pub const MARKUP_HTML: i64 = 2;
+
+/// Text to embed for a note: title, blank line, then the cleaned body.
+pub fn embedding_text(title: &str, body: &str, markup_language: i64) -> String {
+    let body = if markup_language == MARKUP_HTML {
+        html_to_text(body)
+    } else {
+        markdown_to_text(body)
+    };
+    format!("{}\n\n{}", title, body)
+}
+
+/// Split cleaned note text into passages for matching against a query:
+/// paragraphs, with short ones merged into their neighbours and long ones
+/// cut at a word boundary. At most `max` passages, in document order.
+pub fn passages(text: &str, max: usize) -> Vec<String> {
+    const MIN_CHARS: usize = 200;
+    const MAX_CHARS: usize = 1000;
+    let mut out: Vec<String> = Vec::new();
+    let mut current = String::new();
+
+    for paragraph in text.split("\n\n").map(str::trim).filter(|p| !p.is_empty()) {
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(paragraph);
+        while current.chars().count() > MAX_CHARS {
+            let limit = current.char_indices().nth(MAX_CHARS).map_or(current.len(), |(i, _)| i);
+            let cut = current[..limit]
+                .rfind(char::is_whitespace)
+                .filter(|&i| i > 0)
+                .unwrap_or(limit);
+            out.push(current[..cut].trim().to_string());
+            current = current[cut..].trim_start().to_string();
+        }
+        if current.chars().count() >= MIN_CHARS {
+            out.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        out.push(current);
+    }
+    out.truncate(max);
+    out
+}
+
+/// Strip Markdown (and any HTML embedded in it) down to readable text.
+/// Fenced code blocks are kept verbatim apart from the fences.
+pub fn markdown_to_text(md: &str) -> String {
+    let mut out = String::with_capacity(md.len());
+    let mut prose = String::new();
+    let mut in_fence = false;
+
+    for line in md.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            out.push_str(&prose_to_text(&prose));
+            prose.clear();
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            out.push_str(line);
+            out.push('\n');
+        } else {
+            prose.push_str(line);
+            prose.push('\n');
+        }
+    }
+    out.push_str(&prose_to_text(&prose));
+
+    collapse_whitespace(&decode_entities(&out))
+}
+
+/// Convert an HTML document or fragment to text: tags dropped, block
+/// elements become line breaks, `<script>`/`<style>` removed, alt text kept.
+pub fn html_to_text(html: &str) -> String {
+    collapse_whitespace(&decode_entities(&strip_html(html, false)))
+}
+
+/// Markdown outside code fences: drop embedded HTML, then clean line by line.
+fn prose_to_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for line in strip_html(text, true).lines() {
+        let line = line.trim();
+        if is_table_separator(line) || is_horizontal_rule(line) || is_reference_definition(line) {
+            out.push('\n');
+            continue;
+        }
+        let line = strip_inline(strip_block_markers(line));
+        if line.contains('|') {
+            out.push_str(&line.replace('|', "  "));
+        } else {
+            out.push_str(&line);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Remove heading, blockquote, list and checkbox markers from the start of a line.
+fn strip_block_markers(line: &str) -> &str {
+    let mut line = line;
+    loop {
+        let before = line;
+        line = line.trim_start_matches('#').trim_start();
+        line = line.trim_start_matches('>').trim_start();
+        for marker in ["- ", "* ", "+ "] {
+            if let Some(rest) = line.strip_prefix(marker) {
+                line = rest.trim_start();
+            }
+        }
+        let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits > 0 {
+            let rest = &line[digits..];
+            if let Some(rest) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+                line = rest.trim_start();
+            }
+        }
+        for checkbox in ["[ ] ", "[x] ", "[X] "] {
+            if let Some(rest) = line.strip_prefix(checkbox) {
+                line = rest.trim_start();
+            }
+        }
+        if line == before {
+            return line;
+        }
+    }
+}
+
+/// Inline Markdown: links and images become their text, URLs become their
+/// host, `:/id` references, data URIs, backticks and `**`/`~~` markers go.
+/// Single `_`/`*` are left alone so code identifiers survive.
+fn strip_inline(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = String::with_capacity(line.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        // [text](target) and ![alt](target) → text / alt
+        if c == '[' || (c == '!' && chars.get(i + 1) == Some(&'[')) {
+            let open = if c == '!' { i + 1 } else { i };
+            if let Some((text_end, target_end)) = parse_link(&chars, open) {
+                let text: String = chars[open + 1..text_end].iter().collect();
+                out.push_str(&strip_inline(&text));
+                i = target_end + 1;
+                continue;
+            }
+        }
+        if starts_with(&chars, i, "http://") || starts_with(&chars, i, "https://") {
+            let end = token_end(&chars, i);
+            let url: String = chars[i..end].iter().collect();
+            if let Some(host) = crate::search::source_host(&url) {
+                out.push_str(&host);
+            }
+            i = end;
+            continue;
+        }
+        if starts_with(&chars, i, "data:") && at_token_start(&chars, i) {
+            i = token_end(&chars, i);
+            continue;
+        }
+        if starts_with(&chars, i, ":/") && is_resource_id(&chars, i + 2) {
+            i += 2 + 32;
+            continue;
+        }
+        if c == '`' {
+            i += 1;
+            continue;
+        }
+        if matches!(c, '*' | '~') && chars.get(i + 1) == Some(&c) {
+            i += 2;
+            continue;
+        }
+
+        out.push(c);
+        i += 1;
+    }
+    out
+}
+
+/// Given `chars[open] == '['`, find the closing `]` immediately followed by a
+/// `(...)` target. Returns the indices of that `]` and the closing `)`.
+fn parse_link(chars: &[char], open: usize) -> Option<(usize, usize)> {
+    let text_end = matching(chars, open, '[', ']')?;
+    if chars.get(text_end + 1) != Some(&'(') {
+        return None;
+    }
+    let target_end = matching(chars, text_end + 1, '(', ')')?;
+    Some((text_end, target_end))
+}
+
+/// Index of the bracket closing the one at `open`, honouring nesting.
+fn matching(chars: &[char], open: usize, left: char, right: char) -> Option<usize> {
+    let mut depth = 0;
+    for (j, &c) in chars.iter().enumerate().skip(open) {
+        if c == left {
+            depth += 1;
+        } else if c == right {
+            depth -= 1;
+            if depth == 0 {
+                return Some(j);
+            }
+        }
+    }
+    None
+}
+
+/// HTML elements `strip_html` recognises. Anything else between `<` and `>`
+/// (`Vec<String>`, `a<b`) is text.
+const HTML_TAGS: &[&str] = &[
+    "a", "abbr", "address", "article", "aside", "audio", "b", "bdi", "bdo", "blockquote",
+    "body", "br", "button", "caption", "center", "cite", "code", "col", "colgroup", "dd", "del",
+    "details", "dfn", "div", "dl", "dt", "em", "figcaption", "figure", "font", "footer", "form",
+    "h1", "h2", "h3", "h4", "h5", "h6", "head", "header", "hr", "html", "i", "iframe", "img",
+    "input", "ins", "kbd", "label", "li", "link", "main", "mark", "meta", "nav", "noscript",
+    "ol", "p", "picture", "pre", "q", "s", "samp", "script", "section", "small", "source",
+    "span", "strike", "strong", "style", "sub", "summary", "sup", "svg", "table", "tbody", "td",
+    "tfoot", "th", "thead", "time", "title", "tr", "u", "ul", "var", "video", "wbr",
+];
+
+/// Remove HTML tags and comments. Block-level tags become newlines so the
+/// surrounding text doesn't run together; `<img>` keeps its alt text.
+/// In `markdown`, backtick code spans are left as they are and a tag must
+/// close on the line it opens on.
+fn strip_html(text: &str, markdown: bool) -> String {
+    const BLOCK_TAGS: &[&str] = &[
+        "p", "div", "br", "li", "ul", "ol", "tr", "table", "h1", "h2", "h3", "h4", "h5", "h6",
+        "blockquote", "pre", "hr", "section", "article", "header", "footer",
+    ];
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        if markdown && chars[i] == '`' {
+            let end = code_span_end(&chars, i);
+            out.extend(&chars[i..end]);
+            i = end;
+            continue;
+        }
+        if starts_with(&chars, i, "<!--") {
+            i = find_from(&chars, i + 4, "-->").map(|j| j + 3).unwrap_or(chars.len());
+            continue;
+        }
+        let Some((end, name)) = parse_tag(&chars, i, markdown) else {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        };
+
+        let tag: String = chars[i + 1..end].iter().collect();
+        let closing = tag.starts_with('/');
+        i = end + 1;
+
+        if !closing && (name == "script" || name == "style") {
+            // Skip the element's content entirely.
+            i = find_from(&chars, i, &format!("</{name}"))
+                .and_then(|j| tag_end(&chars, j, false))
+                .map(|j| j + 1)
+                .unwrap_or(chars.len());
+        } else if name == "img" {
+            if let Some(alt) = attribute(&tag, "alt") {
+                out.push(' ');
+                out.push_str(&alt);
+                out.push(' ');
+            }
+        } else if BLOCK_TAGS.contains(&name.as_str()) {
+            out.push('\n');
+        } else if name == "td" || name == "th" {
+            out.push(' ');
+        }
+    }
+    out
+}
+
+/// If a real HTML tag opens at `start`, the index of its closing `>` and its
+/// lowercase element name. A tag is a known element name followed by
+/// whitespace, `/` or `>`, then well-formed attributes. In `markdown`, a tag
+/// with attributes glued to a word (`a<b and c>d`) is taken as text.
+fn parse_tag(chars: &[char], start: usize, markdown: bool) -> Option<(usize, String)> {
+    if chars[start] != '<' {
+        return None;
+    }
+    let name_start = if chars.get(start + 1) == Some(&'/') { start + 2 } else { start + 1 };
+    let name_end = (name_start..chars.len())
+        .find(|&j| !chars[j].is_ascii_alphanumeric())
+        .unwrap_or(chars.len());
+    let name: String = chars[name_start..name_end].iter().collect::<String>().to_ascii_lowercase();
+    if !HTML_TAGS.contains(&name.as_str()) {
+        return None;
+    }
+    let after = *chars.get(name_end)?;
+    if !(after.is_whitespace() || after == '/' || after == '>') {
+        return None;
+    }
+    let end = tag_end(chars, start, markdown)?;
+    let attributes: String = chars[name_end..end].iter().collect();
+    if !valid_attributes(attributes.trim_end_matches('/')) {
+        return None;
+    }
+    let glued = start > 0 && chars[start - 1].is_alphanumeric();
+    if markdown && glued && !attributes.trim().is_empty() {
+        return None;
+    }
+    Some((end, name))
+}
+
+/// Whether `text` is a run of `name`, `name=value` or `name="value"` attributes.
+fn valid_attributes(text: &str) -> bool {
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let name_len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '.')))
+            .unwrap_or(rest.len());
+        if name_len == 0 {
+            return false;
+        }
+        rest = rest[name_len..].trim_start();
+        if let Some(value) = rest.strip_prefix('=') {
+            let value = value.trim_start();
+            let value_len = match value.chars().next() {
+                Some(q @ ('"' | '\'')) => match value[1..].find(q) {
+                    Some(close) => close + 2,
+                    None => return false,
+                },
+                _ => value.find(char::is_whitespace).unwrap_or(value.len()),
+            };
+            if value_len == 0 {
+                return false;
+            }
+            rest = value[value_len..].trim_start();
+        }
+    }
+    true
+}
+
+/// Index of the `>` closing the tag that opens at `start`, skipping quoted
+/// attribute values. With `single_line`, gives up at the end of the line.
+fn tag_end(chars: &[char], start: usize, single_line: bool) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    for (j, &c) in chars.iter().enumerate().skip(start + 1) {
+        if single_line && c == '\n' {
+            return None;
+        }
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(j),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// End of the code span whose opening backticks are at `start`: just past
+/// the matching run of backticks, or past the opening run if it never closes.
+fn code_span_end(chars: &[char], start: usize) -> usize {
+    let ticks = chars[start..].iter().take_while(|&&c| c == '`').count();
+    let mut j = start + ticks;
+    while j < chars.len() {
+        let run = chars[j..].iter().take_while(|&&c| c == '`').count();
+        if run == ticks {
+            return j + run;
+        }
+        j += run.max(1);
+    }
+    start + ticks
+}
+
+/// Value of `name="..."` (or single-quoted) inside a tag's source. The name
+/// only matches as a whole attribute, so `alt` doesn't match `data-alt`.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let lower = tag.to_ascii_lowercase();
+    let needle = format!("{name}=");
+    let pos = lower
+        .match_indices(&needle)
+        .map(|(at, _)| at)
+        .find(|&at| at > 0 && lower[..at].ends_with(char::is_whitespace))?
+        + needle.len();
+    let rest = &tag[pos..];
+    let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = &rest[1..];
+    Some(value[..value.find(quote)?].to_string())
+}
+
+/// Decode the HTML entities that commonly appear in clipped notes.
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" | "#39" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|h| u32::from_str_radix(h, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Trim lines, squeeze runs of spaces and keep at most one blank line in a row.
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut blank_run = 0;
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            blank_run += 1;
+            if blank_run > 1 || out.is_empty() {
+                continue;
+            }
+        } else {
+            blank_run = 0;
+        }
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out.trim_end().to_string()
+}
+
+fn is_table_separator(line: &str) -> bool {
+    line.contains('-')
+        && line.contains('|')
+        && line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+fn is_horizontal_rule(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3
+        && (compact.chars().all(|c| c == '-')
+            || compact.chars().all(|c| c == '*')
+            || compact.chars().all(|c| c == '_'))
+}
+
+/// `[label]: https://…` link reference definitions.
+fn is_reference_definition(line: &str) -> bool {
+    line.starts_with('[') && line.contains("]:")
+}
+
+/// Joplin resource/note IDs: 32 lowercase hex chars.
+fn is_resource_id(chars: &[char], start: usize) -> bool {
+    chars.len() >= start + 32
+        && chars[start..start + 32]
+            .iter()
+            .all(|c| matches!(c, '0'..='9' | 'a'..='f'))
+}
+
+/// End of a URL-like token: the next whitespace or closing delimiter.
+fn token_end(chars: &[char], start: usize) -> usize {
+    chars[start..]
+        .iter()
+        .position(|c| c.is_whitespace() || matches!(c, ')' | ']' | '>' | '<' | '"' | '\''))
+        .map(|p| start + p)
+        .unwrap_or(chars.len())
+}
+
+/// Whether `chars[at]` starts a token: at the start of the text, or after
+/// whitespace or an opening `(` or `"`.
+fn at_token_start(chars: &[char], at: usize) -> bool {
+    at == 0 || chars[at - 1].is_whitespace() || matches!(chars[at - 1], '(' | '"')
+}
+
+fn starts_with(chars: &[char], at: usize, needle: &str) -> bool {
+    needle
+        .chars()
+        .enumerate()
+        .all(|(k, n)| chars.get(at + k) == Some(&n))
+}
+
+fn find_from(chars: &[char], from: usize, needle: &str) -> Option<usize> {
+    (from..chars.len()).find(|&j| starts_with(chars, j, needle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_fences_are_kept_verbatim() {
+        let md = "Intro\n\n```rust\nlet v: Vec<String> = vec![];\n```\nAfter";
+        assert_eq!(markdown_to_text(md), "Intro\n\nlet v: Vec<String> = vec![];\nAfter");
+    }
+
+    #[test]
+    fn inline_code_keeps_generics_and_tags() {
+        assert_eq!(
+            markdown_to_text("Returns `Vec<String>` or `Option<b>`"),
+            "Returns Vec<String> or Option<b>"
+        );
+        assert_eq!(markdown_to_text("A Vec<String> of names"), "A Vec<String> of names");
+    }
+
+    #[test]
+    fn comparisons_in_prose_survive() {
+        assert_eq!(markdown_to_text("if a < b and c > d"), "if a < b and c > d");
+        assert_eq!(markdown_to_text("if a<b … c>d then"), "if a<b … c>d then");
+        assert_eq!(markdown_to_text("if a<b and c>d then"), "if a<b and c>d then");
+        // An apostrophe in a false tag must not swallow the following lines.
+        assert_eq!(
+            markdown_to_text("x <b don't\nsecond line\n'> end"),
+            "x <b don't\nsecond line\n'> end"
+        );
+    }
+
+    #[test]
+    fn links_and_images_become_their_text() {
+        assert_eq!(
+            markdown_to_text("See [the docs](https://example.com/a/b) and ![a cat](cat.png)"),
+            "See the docs and a cat"
+        );
+        assert_eq!(
+            markdown_to_text("Visit https://www.example.com/page?x=1"),
+            "Visit example.com"
+        );
+    }
+
+    #[test]
+    fn resource_ids_are_dropped() {
+        let md = "![scan](:/0123456789abcdef0123456789abcdef) and \
+                  [note](:/fedcba9876543210fedcba9876543210)";
+        assert_eq!(markdown_to_text(md), "scan and note");
+        assert_eq!(markdown_to_text("raw :/0123456789abcdef0123456789abcdef ref"), "raw ref");
+    }
+
+    #[test]
+    fn data_uris_are_dropped_but_not_words_ending_in_data() {
+        assert_eq!(markdown_to_text("inline data:image/png;base64,iVBORw0K image"), "inline image");
+        assert_eq!(markdown_to_text("(data:text/plain,hi) end"), "() end");
+        assert_eq!(markdown_to_text("metadata: title, tags"), "metadata: title, tags");
+    }
+
+    #[test]
+    fn entities_are_decoded() {
+        assert_eq!(
+            html_to_text("Fish &amp; chips &lt;3 &#39;x&#x27; &nbsp;ok"),
+            "Fish & chips <3 'x' ok"
+        );
+    }
+
+    #[test]
+    fn script_and_style_content_is_removed() {
+        let html = "<p>Before</p><script type=\"text/javascript\">var a = 1 < 2;</script>\
+                    <style>p > a { color: red }</style><p>After</p>";
+        assert_eq!(html_to_text(html), "Before\n\nAfter");
+    }
+
+    #[test]
+    fn html_tags_are_stripped_and_alt_text_kept() {
+        let html = "<div class=\"x\">Hello <b>world</b><br/>\
+                    <img data-alt=\"no\" src=\"a.png\" alt=\"A chart\"></div>";
+        assert_eq!(html_to_text(html), "Hello world\nA chart");
+        assert_eq!(
+            markdown_to_text("Text with <span style=\"color: red\">red</span> words"),
+            "Text with red words"
+        );
+    }
+
+    #[test]
+    fn attribute_matches_whole_names_only() {
+        assert_eq!(attribute("img data-alt=\"no\" xalt='no'", "alt"), None);
+        assert_eq!(attribute("img data-alt=\"no\" alt='yes'", "alt"), Some("yes".to_string()));
+    }
+}
@@ -0,0 +1,281 @@
+use anyhow::Result;
+use std::path::Path;
+
+use crate::types::RevisionMetadata;
+
+/// A raw row from Joplin's `revisions` table.
+/// `title_diff`/`body_diff` are diff-match-patch patches against the previous
+/// revision of the same note (the first revision is patched against "").
+pub struct RevisionRow {
+    pub id: String,
+    pub note_id: String,
+    pub item_updated_time: i64,
+    pub title_diff: String,
+    pub body_diff: String,
+}
+
+/// A past version of a note, reconstructed by replaying its revision chain.
+pub struct NoteRevision {
+    pub id: String,
+    pub note_id: String,
+    pub title: String,
+    pub body: String,
+    pub revision_time: i64, // Unix ms — note's updated_time when the revision was taken
+}
+
+/// Replay the revision chains in `rows` and return one reconstructed
+/// revision per row. `rows` must be grouped by note and sorted by
+/// `item_updated_time` ascending within each note (as `db::get_revision_rows` does).
+/// A note whose chain fails to apply is skipped from the broken revision on.
+pub fn reconstruct_all(rows: Vec<RevisionRow>) -> Vec<NoteRevision> {
+    let mut out = Vec::with_capacity(rows.len());
+    let mut current_note = String::new();
+    let mut title = String::new();
+    let mut body = String::new();
+    let mut broken = false;
+
+    for row in rows {
+        if row.note_id != current_note {
+            current_note = row.note_id.clone();
+            title.clear();
+            body.clear();
+            broken = false;
+        }
+        if broken {
+            continue;
+        }
+        let patched = apply_patch(&title, &row.title_diff)
+            .and_then(|t| apply_patch(&body, &row.body_diff).map(|b| (t, b)));
+        match patched {
+            Ok((t, b)) => {
+                title = t;
+                body = b;
+            }
+            Err(e) => {
+                tracing::warn!("Skipping revision chain from {}: {e}", row.id);
+                broken = true;
+                continue;
+            }
+        }
+        out.push(NoteRevision {
+            id: row.id,
+            note_id: row.note_id,
+            title: title.clone(),
+            body: body.clone(),
+            revision_time: row.item_updated_time,
+        });
+    }
+    out
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// One hunk of a diff-match-patch patch. Text is kept in UTF-16 code units
+/// because that is what the JavaScript library counts offsets in.
+struct Hunk {
+    start: usize,
+    diffs: Vec<(Op, Vec<u16>)>,
+}
+
+/// Apply a diff-match-patch patch (`patch_toText` format) to `base`.
+/// Joplin generates patches from the exact previous text, so hunks are applied
+/// at their recorded offsets; if the context doesn't match there, the hunk is
+/// searched for once before giving up. Hunks are applied in order, so each
+/// one's offset is taken in the patched text (`start2`), as the library does.
+pub fn apply_patch(base: &str, patch: &str) -> Result<String> {
+    if patch.is_empty() {
+        return Ok(base.to_string());
+    }
+    let mut buf: Vec<u16> = base.encode_utf16().collect();
+
+    for hunk in parse_patch(patch)? {
+        // Text the hunk expects to find: everything it keeps or removes.
+        let expected: Vec<u16> = hunk
+            .diffs
+            .iter()
+            .filter(|(op, _)| *op != Op::Insert)
+            .flat_map(|(_, t)| t.iter().copied())
+            .collect();
+
+        let start = if matches_at(&buf, hunk.start, &expected) {
+            hunk.start
+        } else {
+            find(&buf, &expected).ok_or_else(|| anyhow::anyhow!("patch context not found"))?
+        };
+
+        let mut pos = start;
+        for (op, text) in hunk.diffs {
+            match op {
+                Op::Equal => pos += text.len(),
+                Op::Delete => {
+                    buf.drain(pos..pos + text.len());
+                }
+                Op::Insert => {
+                    let len = text.len();
+                    buf.splice(pos..pos, text);
+                    pos += len;
+                }
+            }
+        }
+    }
+
+    Ok(String::from_utf16_lossy(&buf))
+}
+
+/// Parse `patch_toText` output into hunks.
+fn parse_patch(patch: &str) -> Result<Vec<Hunk>> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+
+    for line in patch.split('\n') {
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix("@@ -") {
+            // "@@ -start1[,len1] +start2[,len2] @@" — only start2 matters here.
+            let coords = header
+                .split(' ')
+                .nth(1)
+                .and_then(|c| c.strip_prefix('+'))
+                .ok_or_else(|| anyhow::anyhow!("malformed patch header"))?;
+            let (start, len) = match coords.split_once(',') {
+                Some((s, l)) => (s, Some(l)),
+                None => (coords, None),
+            };
+            let start: usize = start.parse()?;
+            // Offsets are 1-based except for empty ranges ("N,0").
+            let start = if len == Some("0") { start } else { start.saturating_sub(1) };
+            hunks.push(Hunk { start, diffs: Vec::new() });
+            continue;
+        }
+
+        let hunk = hunks
+            .last_mut()
+            .ok_or_else(|| anyhow::anyhow!("patch body before header"))?;
+        let mut chars = line.chars();
+        let op = match chars.next() {
+            Some(' ') => Op::Equal,
+            Some('-') => Op::Delete,
+            Some('+') => Op::Insert,
+            _ => anyhow::bail!("invalid patch line"),
+        };
+        let text = percent_decode(chars.as_str())?;
+        hunk.diffs.push((op, text.encode_utf16().collect()));
+    }
+
+    Ok(hunks)
+}
+
+/// Reverse of JavaScript's `encodeURI` as used by diff-match-patch.
+fn percent_decode(s: &str) -> Result<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3])?;
+            out.push(u8::from_str_radix(hex, 16)?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(String::from_utf8(out)?)
+}
+
+fn matches_at(buf: &[u16], pos: usize, needle: &[u16]) -> bool {
+    buf.get(pos..pos + needle.len()) == Some(needle)
+}
+
+fn find(buf: &[u16], needle: &[u16]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(buf.len());
+    }
+    buf.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Load the revision metadata saved alongside the revision index.
+/// None if it is missing or unreadable.
+pub fn load_metadata(path: &Path) -> Option<Vec<RevisionMetadata>> {
+    crate::storage::load_json(path)
+}
+
+/// Persist revision metadata atomically (write temp file, then rename).
+pub fn save_metadata(metadata: &[RevisionMetadata], path: &Path) -> Result<()> {
+    crate::storage::save_json_atomic(metadata, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(id: &str, note_id: &str, time: i64, body_diff: &str) -> RevisionRow {
+        RevisionRow {
+            id: id.to_string(),
+            note_id: note_id.to_string(),
+            item_updated_time: time,
+            title_diff: String::new(),
+            body_diff: body_diff.to_string(),
+        }
+    }
+
+    #[test]
+    fn patch_text_is_url_decoded() {
+        let patch = "@@ -0,0 +1,14 @@\n+50%25 off, caf%C3%A9%0A\n";
+        assert_eq!(apply_patch("", patch).unwrap(), "50% off, café\n");
+    }
+
+    #[test]
+    fn later_hunks_are_placed_in_the_patched_text() {
+        // The second hunk's context also occurs before it; its offset is
+        // only right once the first hunk's insertion is counted.
+        let patch = "@@ -1,2 +1,5 @@\n+123\n ab\n@@ -7,5 +10,5 @@\n ab \n-cd\n+ef\n";
+        assert_eq!(apply_patch("ab cd ab cd", patch).unwrap(), "123ab cd ab ef");
+    }
+
+    #[test]
+    fn hunk_that_moved_is_found_by_its_context() {
+        let patch = "@@ -1,7 +1,7 @@\n The \n-fox\n+cat\n";
+        assert_eq!(apply_patch("Once: The fox", patch).unwrap(), "Once: The cat");
+    }
+
+    #[test]
+    fn hunk_without_its_context_fails() {
+        let patch = "@@ -1,7 +1,7 @@\n The \n-fox\n+cat\n";
+        assert!(apply_patch("A dog", patch).is_err());
+        assert!(apply_patch("A dog", "-fox\n").is_err());
+    }
+
+    #[test]
+    fn chains_are_replayed_from_an_empty_body() {
+        let rows = vec![
+            row("r1", "a", 1, "@@ -0,0 +1,5 @@\n+Hello\n"),
+            row("r2", "a", 2, "@@ -1,5 +1,11 @@\n Hello\n+ world\n"),
+            row("r3", "b", 1, "@@ -0,0 +1,3 @@\n+One\n"),
+        ];
+        let bodies: Vec<(String, String)> =
+            reconstruct_all(rows).into_iter().map(|r| (r.id, r.body)).collect();
+        assert_eq!(
+            bodies,
+            [("r1", "Hello"), ("r2", "Hello world"), ("r3", "One")]
+                .map(|(id, body)| (id.to_string(), body.to_string()))
+        );
+    }
+
+    #[test]
+    fn broken_chain_is_skipped_from_the_failed_revision_on() {
+        let rows = vec![
+            row("r1", "a", 1, "@@ -0,0 +1,5 @@\n+Hello\n"),
+            row("r2", "a", 2, "@@ -1,3 +1,3 @@\n-Bye\n+Hey\n"),
+            row("r3", "a", 3, "@@ -1,5 +1,11 @@\n Hello\n+ world\n"),
+            row("r4", "b", 1, "@@ -0,0 +1,3 @@\n+One\n"),
+        ];
+        let ids: Vec<String> = reconstruct_all(rows).into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, ["r1", "r4"]);
+    }
+}
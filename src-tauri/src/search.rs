@@ -0,0 +1,248 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::links::LinkGraph;
+use crate::types::{
+    NoteMetadata, RankingBreakdown, RankingWeights, ScoreBoost, SearchOptions, SearchResult,
+    SortBy,
+};
+use crate::usage::UsageData;
+
+impl SearchOptions {
+    /// True if any metadata filter is set. Sorting alone doesn't count.
+    pub fn has_filters(&self) -> bool {
+        self.source_domain.is_some()
+            || self.author.is_some()
+            || self.created_after.is_some()
+            || self.created_before.is_some()
+            || self.updated_after.is_some()
+            || self.updated_before.is_some()
+            || self.has_location.is_some()
+    }
+
+    /// Human-readable list of the filters that are set, for `ScoreExplanation`.
+    pub fn describe_filters(&self) -> Vec<String> {
+        let date = |label: &str, t: Option<i64>| t.map(|t| format!("{label} {t}"));
+        [
+            self.source_domain.as_ref().map(|d| format!("source_domain = {d}")),
+            self.author.as_ref().map(|a| format!("author ~ {a}")),
+            date("created >=", self.created_after),
+            date("created <=", self.created_before),
+            date("updated >=", self.updated_after),
+            date("updated <=", self.updated_before),
+            self.has_location.map(|l| format!("has_location = {l}")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Whether a note passes every filter that is set.
+    pub fn matches(&self, note: &NoteMetadata) -> bool {
+        if let Some(domain) = &self.source_domain {
+            let domain = domain.trim().trim_start_matches("www.").to_lowercase();
+            match source_host(&note.source_url) {
+                Some(host) if host == domain || host.ends_with(&format!(".{domain}")) => {}
+                _ => return false,
+            }
+        }
+        if let Some(author) = &self.author {
+            if !note.author.to_lowercase().contains(&author.to_lowercase()) {
+                return false;
+            }
+        }
+        let in_range = |t: i64, after: Option<i64>, before: Option<i64>| {
+            after.is_none_or(|a| t >= a) && before.is_none_or(|b| t <= b)
+        };
+        if !in_range(note.created_time, self.created_after, self.created_before) {
+            return false;
+        }
+        if !in_range(note.user_updated_time, self.updated_after, self.updated_before) {
+            return false;
+        }
+        if let Some(wanted) = self.has_location {
+            let has_location = note.latitude != 0.0 || note.longitude != 0.0;
+            if has_location != wanted {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl RankingWeights {
+    /// True if any signal beyond similarity is switched on.
+    pub fn is_active(&self) -> bool {
+        self.recency > 0.0 || self.usage > 0.0 || self.pinned > 0.0
+    }
+}
+
+const DAY_MS: f32 = 86_400_000.0;
+
+/// Add the weighted ranking signals to each result's similarity score,
+/// record the breakdown, and re-rank.
+pub fn apply_ranking(
+    results: &mut [SearchResult],
+    weights: &RankingWeights,
+    usage: &UsageData,
+    now_ms: i64,
+) {
+    // 1 at age 0, ½ after one half-life. Future timestamps count as now.
+    let decay = |time: i64, half_life_days: f32| {
+        let age_days = (now_ms - time).max(0) as f32 / DAY_MS;
+        0.5f32.powf(age_days / half_life_days.max(0.01))
+    };
+    for r in results.iter_mut() {
+        let recency = decay(r.note.user_updated_time, weights.recency_half_life_days);
+        let opened: f32 = usage
+            .opens
+            .get(&r.note.id)
+            .into_iter()
+            .flatten()
+            .map(|&t| decay(t, weights.usage_half_life_days))
+            .sum();
+        let pinned = if usage.pinned.contains(&r.note.id) { 1.0 } else { 0.0 };
+
+        let breakdown = RankingBreakdown {
+            similarity: r.score,
+            recency: weights.recency * recency,
+            usage: weights.usage * opened.min(1.0),
+            pinned: weights.pinned * pinned,
+        };
+        r.score = breakdown.similarity + breakdown.recency + breakdown.usage + breakdown.pinned;
+        if let Some(explanation) = &mut r.explanation {
+            for (signal, value) in [
+                ("recency", breakdown.recency),
+                ("usage", breakdown.usage),
+                ("pinned", breakdown.pinned),
+            ] {
+                if value > 0.0 {
+                    explanation.boosts.push(ScoreBoost { signal: signal.to_string(), value });
+                }
+            }
+        }
+        r.ranking = Some(breakdown);
+    }
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+}
+
+/// Reorder results by the requested date. Stable, so notes with equal
+/// timestamps keep their relevance order.
+pub fn sort_results(results: &mut [SearchResult], options: &SearchOptions) {
+    let key = match options.sort_by {
+        SortBy::Relevance => return,
+        SortBy::CreatedTime => |r: &SearchResult| r.note.created_time,
+        SortBy::UpdatedTime => |r: &SearchResult| r.note.user_updated_time,
+    };
+    if options.ascending {
+        results.sort_by_key(key);
+    } else {
+        results.sort_by_key(|r| std::cmp::Reverse(key(r)));
+    }
+}
+
+/// Weight of the link boost: a result linked from a hit scoring 0.8 gains 0.04.
+const LINK_BOOST: f32 = 0.05;
+
+/// Raise the score of results that other results link to, then re-rank.
+/// Each result takes the best score among the hits linking to it, so a note
+/// referenced by many weak hits isn't pushed above one cited by a strong hit.
+pub fn boost_linked(results: &mut [SearchResult], graph: &LinkGraph) {
+    let scores: HashMap<&str, f32> = results
+        .iter()
+        .map(|r| (r.note.id.as_str(), r.score))
+        .collect();
+    let boosts: Vec<f32> = results
+        .iter()
+        .map(|r| {
+            graph
+                .backlinks(&r.note.id)
+                .filter_map(|id| scores.get(id.as_str()).copied())
+                .fold(0.0, f32::max)
+                * LINK_BOOST
+        })
+        .collect();
+    for (r, boost) in results.iter_mut().zip(boosts) {
+        r.score += boost;
+        if let Some(explanation) = r.explanation.as_mut().filter(|_| boost > 0.0) {
+            explanation.boosts.push(ScoreBoost { signal: "linked".to_string(), value: boost });
+        }
+    }
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+}
+
+/// Re-order `results` with Maximal Marginal Relevance and keep the best `k`.
+/// Each pick maximises `lambda · score − (1 − lambda) · max similarity to the
+/// results already picked`, so near-identical notes stop crowding each other
+/// out. `vectors[i]` is the embedding of `results[i]`; results without one
+/// are treated as unlike everything.
+pub fn diversify(
+    results: Vec<SearchResult>,
+    vectors: &[Option<Vec<f32>>],
+    lambda: f32,
+    k: usize,
+) -> Vec<SearchResult> {
+    let lambda = lambda.clamp(0.0, 1.0);
+    let mut remaining: Vec<(usize, SearchResult)> = results.into_iter().enumerate().collect();
+    // Highest similarity of each remaining candidate to anything picked so far.
+    let mut redundancy = vec![0.0f32; vectors.len()];
+    let mut picked = Vec::with_capacity(k.min(remaining.len()));
+
+    while picked.len() < k && !remaining.is_empty() {
+        let mmr = |(i, r): &(usize, SearchResult)| {
+            lambda * r.score - (1.0 - lambda) * redundancy[*i]
+        };
+        let best = (0..remaining.len())
+            .max_by(|&a, &b| {
+                mmr(&remaining[a])
+                    .partial_cmp(&mmr(&remaining[b]))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    // Ties go to the earlier (more relevant) candidate.
+                    .then(b.cmp(&a))
+            })
+            .unwrap_or(0);
+        let (i, result) = remaining.remove(best);
+        if let Some(v) = &vectors[i] {
+            for (j, _) in &remaining {
+                if let Some(w) = &vectors[*j] {
+                    redundancy[*j] = redundancy[*j].max(crate::index::cosine_similarity(v, w));
+                }
+            }
+        }
+        picked.push(result);
+    }
+    picked
+}
+
+/// Share of the query's distinct words (2+ characters) found in `text`.
+/// None if the query has no such words.
+pub fn lexical_score(query: &str, text: &str) -> Option<f32> {
+    let words = |s: &str| -> HashSet<String> {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| w.chars().count() >= 2)
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let query_words = words(query);
+    if query_words.is_empty() {
+        return None;
+    }
+    let text_words = words(text);
+    let found = query_words.iter().filter(|w| text_words.contains(*w)).count();
+    Some(found as f32 / query_words.len() as f32)
+}
+
+/// Lowercased host of a source URL without a leading "www.", e.g.
+/// "https://www.github.com/foo" → "github.com". None for empty or non-URL values.
+pub fn source_host(url: &str) -> Option<String> {
+    let rest = url.split_once("://").map(|(_, r)| r)?;
+    let host = rest
+        .split(['/', '?', '#'])
+        .next()?
+        .rsplit('@')
+        .next()?
+        .split(':')
+        .next()?
+        .to_lowercase();
+    let host = host.strip_prefix("www.").map(str::to_string).unwrap_or(host);
+    (!host.is_empty()).then_some(host)
+}
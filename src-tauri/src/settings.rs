@@ -0,0 +1,15 @@
+use anyhow::Result;
+use std::path::Path;
+
+use crate::types::Settings;
+
+/// Load settings from disk. Falls back to defaults if the file is missing or unreadable.
+pub fn load(path: &Path) -> Settings {
+    crate::storage::load_json(path).unwrap_or_default()
+}
+
+/// Persist settings atomically (write temp file, then rename).
+pub fn save(settings: &Settings, path: &Path) -> Result<()> {
+    // Pretty-printed: the file is meant to be readable by hand.
+    crate::storage::write_atomic(path, serde_json::to_string_pretty(settings)?)
+}
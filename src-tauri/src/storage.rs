@@ -0,0 +1,28 @@
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+
+/// Write `bytes` to `path` atomically: to a `.tmp` file alongside it, then
+/// renamed over it, so a crash mid-write leaves the previous file intact.
+pub fn write_atomic(path: &Path, bytes: impl AsRef<[u8]>) -> Result<()> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    let tmp_path = path.with_extension(format!("{extension}.tmp"));
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&tmp_path, bytes)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Persist `value` as JSON atomically (see `write_atomic`).
+pub fn save_json_atomic<T: Serialize + ?Sized>(value: &T, path: &Path) -> Result<()> {
+    write_atomic(path, serde_json::to_vec(value)?)
+}
+
+/// Load a JSON file. None if it is missing or unreadable.
+pub fn load_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let json = std::fs::read(path).ok()?;
+    serde_json::from_slice(&json).ok()
+}
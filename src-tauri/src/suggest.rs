@@ -0,0 +1,153 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::index::SearchIndex;
+use crate::types::{NoteMetadata, NoteSuggestion, NotebookSuggestion, TagSuggestion};
+
+/// Neighbours that vote on a note's tags and notebook.
+const NEIGHBOURS: usize = 15;
+/// Neighbours less similar than this don't vote.
+const MIN_SIMILARITY: f32 = 0.5;
+/// Suggestions below this confidence are left out.
+const MIN_CONFIDENCE: f32 = 0.2;
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Tag and notebook suggestions for `note_id`, from a similarity-weighted vote
+/// of its nearest neighbours. None if the note isn't in the index.
+pub fn suggest_for_note(
+    index: &SearchIndex,
+    note_id: &str,
+    notes: &HashMap<String, NoteMetadata>,
+    note_tags: &HashMap<String, Vec<String>>,
+    notebooks: &HashMap<String, String>,
+) -> Option<NoteSuggestion> {
+    let note = notes.get(note_id)?;
+    let vector = index.vector(note_id)?;
+
+    // +1: the note itself comes back as its own nearest neighbour.
+    let neighbours: Vec<(&NoteMetadata, f32)> = index
+        .search(vector, NEIGHBOURS + 1)
+        .ok()?
+        .into_iter()
+        .filter(|hit| hit.note_id != note_id && hit.score >= MIN_SIMILARITY)
+        .filter_map(|hit| Some((notes.get(&hit.note_id)?, hit.score)))
+        .collect();
+    Some(vote(note, &neighbours, note_tags, notebooks))
+}
+
+/// Suggestions for `note` from `neighbours` and their similarity to it.
+fn vote(
+    note: &NoteMetadata,
+    neighbours: &[(&NoteMetadata, f32)],
+    note_tags: &HashMap<String, Vec<String>>,
+    notebooks: &HashMap<String, String>,
+) -> NoteSuggestion {
+    let own_tags: HashSet<&String> = note_tags.get(&note.id).into_iter().flatten().collect();
+    let mut tag_votes: HashMap<&str, f32> = HashMap::new();
+    let mut tagged_weight = 0.0;
+    let mut notebook_votes: HashMap<&str, f32> = HashMap::new();
+    let mut total_weight = 0.0;
+    for (other, similarity) in neighbours {
+        if let Some(tags) = note_tags.get(&other.id) {
+            tagged_weight += similarity;
+            for tag in tags.iter().filter(|t| !own_tags.contains(t)) {
+                *tag_votes.entry(tag.as_str()).or_default() += similarity;
+            }
+        }
+        total_weight += similarity;
+        *notebook_votes.entry(other.parent_id.as_str()).or_default() += similarity;
+    }
+
+    NoteSuggestion {
+        note_id: note.id.clone(),
+        title: note.title.clone(),
+        tags: ranked(tag_votes, tagged_weight)
+            .map(|(tag, confidence)| TagSuggestion { tag: tag.to_string(), confidence })
+            .collect(),
+        notebooks: ranked(notebook_votes, total_weight)
+            .filter_map(|(id, confidence)| {
+                Some(NotebookSuggestion {
+                    notebook_id: id.to_string(),
+                    notebook: notebooks.get(id)?.clone(),
+                    confidence,
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Votes as shares of `total`, best first, above `MIN_CONFIDENCE`.
+fn ranked(votes: HashMap<&str, f32>, total: f32) -> impl Iterator<Item = (&str, f32)> {
+    let mut shares: Vec<(&str, f32)> = votes
+        .into_iter()
+        .map(|(key, weight)| (key, if total > 0.0 { weight / total } else { 0.0 }))
+        .filter(|(_, share)| *share >= MIN_CONFIDENCE)
+        .collect();
+    shares.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.0.cmp(b.0))
+    });
+    shares.into_iter().take(MAX_SUGGESTIONS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Note;
+
+    fn metadata(id: &str, notebook: &str) -> NoteMetadata {
+        Note { id: id.to_string(), parent_id: notebook.to_string(), ..Default::default() }
+            .metadata()
+    }
+
+    #[test]
+    fn neighbours_vote_by_similarity() {
+        let note = metadata("n", "nb1");
+        let (a, b, c) = (metadata("a", "nb1"), metadata("b", "nb1"), metadata("c", "nb2"));
+        let note_tags: HashMap<String, Vec<String>> = [
+            ("n", vec!["cli"]),
+            ("a", vec!["rust"]),
+            ("b", vec!["rust", "cli"]),
+            ("c", vec!["go"]),
+        ]
+        .into_iter()
+        .map(|(id, tags)| (id.to_string(), tags.into_iter().map(String::from).collect()))
+        .collect();
+        let notebooks: HashMap<String, String> =
+            ["nb1", "nb2"].map(|id| (id.to_string(), id.to_uppercase())).into();
+
+        let suggestion = vote(&note, &[(&a, 0.9), (&b, 0.8), (&c, 0.6)], &note_tags, &notebooks);
+
+        // The note already has "cli".
+        let tags: Vec<(&str, f32)> =
+            suggestion.tags.iter().map(|t| (t.tag.as_str(), t.confidence)).collect();
+        assert_eq!(tags.iter().map(|t| t.0).collect::<Vec<_>>(), ["rust", "go"]);
+        assert!((tags[0].1 - 1.7 / 2.3).abs() < 1e-6);
+        assert!((tags[1].1 - 0.6 / 2.3).abs() < 1e-6);
+
+        let books: Vec<(&str, f32)> =
+            suggestion.notebooks.iter().map(|b| (b.notebook.as_str(), b.confidence)).collect();
+        assert_eq!(books.iter().map(|b| b.0).collect::<Vec<_>>(), ["NB1", "NB2"]);
+        assert!((books[0].1 - 1.7 / 2.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn untagged_neighbours_only_vote_on_the_notebook() {
+        let note = metadata("n", "nb1");
+        let (a, b) = (metadata("a", "nb2"), metadata("b", "nb2"));
+        let note_tags: HashMap<String, Vec<String>> =
+            [("a".to_string(), vec!["x".to_string()])].into();
+        let notebooks: HashMap<String, String> = [("nb2".to_string(), "NB2".to_string())].into();
+
+        let suggestion = vote(&note, &[(&a, 0.6), (&b, 0.9)], &note_tags, &notebooks);
+        assert_eq!(suggestion.tags[0].confidence, 1.0);
+        assert_eq!(suggestion.notebooks[0].confidence, 1.0);
+    }
+
+    #[test]
+    fn weak_votes_are_left_out() {
+        let votes: HashMap<&str, f32> = [("a", 3.0), ("b", 1.5), ("c", 0.5)].into();
+        let shares: Vec<(&str, f32)> = ranked(votes, 5.0).collect();
+        assert_eq!(shares, [("a", 0.6), ("b", 0.3)]);
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::index::normalize;
+use crate::types::{NoteMetadata, TopicCluster, TopicMember};
+
+/// Lloyd iterations before giving up on convergence.
+const MAX_ITERATIONS: usize = 50;
+/// Keywords and representative titles reported per cluster.
+const LABEL_SIZE: usize = 5;
+const REPRESENTATIVE_TITLES: usize = 3;
+/// A notebook must hold at least this share of a cluster before members
+/// filed elsewhere are flagged.
+const DOMINANT_NOTEBOOK_SHARE: f32 = 0.5;
+
+const STOPWORDS: &[&str] = &[
+    "the", "and", "for", "with", "from", "into", "about", "your", "you", "are", "was", "were",
+    "this", "that", "these", "those", "how", "what", "why", "when", "where", "who", "which",
+    "not", "but", "all", "any", "can", "will", "have", "has", "had", "our", "their", "its",
+    "new", "notes", "note", "untitled",
+];
+
+/// Cluster the vault with spherical k-means (cosine similarity) and label
+/// each cluster. `vectors` are the embeddings of the notes to cluster;
+/// notes missing from `notes` are skipped. `k` defaults to √(n/2).
+/// Deterministic: the same vault gives the same clusters.
+pub fn build_topic_map(
+    vectors: Vec<(String, Vec<f32>)>,
+    notes: &HashMap<String, NoteMetadata>,
+    notebooks: &HashMap<String, String>,
+    k: Option<usize>,
+) -> Vec<TopicCluster> {
+    let mut points: Vec<(String, Vec<f32>)> = vectors
+        .into_iter()
+        .filter(|(id, _)| notes.contains_key(id))
+        .collect();
+    if points.is_empty() {
+        return vec![];
+    }
+    // HashMap iteration order is random; sort so the seeding is reproducible.
+    points.sort_by(|a, b| a.0.cmp(&b.0));
+    for (_, v) in &mut points {
+        normalize(v);
+    }
+
+    let n = points.len();
+    let k = k
+        .unwrap_or_else(|| ((n as f32 / 2.0).sqrt().round() as usize).clamp(2, 40))
+        .clamp(1, n);
+    let vecs: Vec<&[f32]> = points.iter().map(|(_, v)| v.as_slice()).collect();
+    let (assignment, centroids) = kmeans(&vecs, k);
+
+    // Group members and their similarity to the centre.
+    let mut groups: Vec<Vec<(usize, f32)>> = vec![vec![]; k];
+    for (i, &c) in assignment.iter().enumerate() {
+        groups[c].push((i, dot(vecs[i], &centroids[c])));
+    }
+    groups.retain(|g| !g.is_empty());
+    for g in &mut groups {
+        g.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    }
+    groups.sort_by_key(|g| std::cmp::Reverse(g.len()));
+
+    let title = |i: usize| notes[&points[i].0].title.as_str();
+    let keywords = cluster_keywords(&groups, title);
+
+    groups
+        .iter()
+        .zip(keywords)
+        .enumerate()
+        .map(|(id, (group, keywords))| {
+            let parent = |i: usize| notes[&points[i].0].parent_id.as_str();
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+            for &(i, _) in group {
+                *counts.entry(parent(i)).or_default() += 1;
+            }
+            let dominant = counts
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0)))
+                .filter(|(_, count)| {
+                    group.len() >= 3
+                        && *count as f32 / group.len() as f32 >= DOMINANT_NOTEBOOK_SHARE
+                })
+                .map(|(parent_id, _)| parent_id);
+
+            TopicCluster {
+                id,
+                keywords,
+                representative_titles: group
+                    .iter()
+                    .take(REPRESENTATIVE_TITLES)
+                    .map(|&(i, _)| title(i).to_string())
+                    .collect(),
+                top_notebook: dominant.and_then(|p| notebooks.get(p).cloned()),
+                members: group
+                    .iter()
+                    .map(|&(i, similarity)| TopicMember {
+                        note_id: points[i].0.clone(),
+                        title: title(i).to_string(),
+                        notebook: notebooks.get(parent(i)).cloned().unwrap_or_default(),
+                        similarity,
+                        outside_top_notebook: dominant.is_some_and(|p| p != parent(i)),
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Spherical k-means with k-means++ seeding. Inputs must be unit vectors.
+/// Returns each point's cluster and the (unit) cluster centres.
+fn kmeans(vecs: &[&[f32]], k: usize) -> (Vec<usize>, Vec<Vec<f32>>) {
+    let mut rng = SplitMix64(0x5EED);
+    let mut centroids: Vec<Vec<f32>> = vec![vecs[rng.below(vecs.len())].to_vec()];
+    // Squared distance on the unit sphere is 2 − 2·cos.
+    let mut nearest: Vec<f32> = vecs.iter().map(|v| 2.0 - 2.0 * dot(v, &centroids[0])).collect();
+    while centroids.len() < k {
+        let total: f32 = nearest.iter().map(|d| d.max(0.0)).sum();
+        let next = if total > 0.0 {
+            let mut target = rng.unit() * total;
+            nearest
+                .iter()
+                .position(|d| {
+                    target -= d.max(0.0);
+                    target <= 0.0
+                })
+                .unwrap_or(vecs.len() - 1)
+        } else {
+            rng.below(vecs.len())
+        };
+        centroids.push(vecs[next].to_vec());
+        for (d, v) in nearest.iter_mut().zip(vecs) {
+            *d = d.min(2.0 - 2.0 * dot(v, &centroids[centroids.len() - 1]));
+        }
+    }
+
+    let mut assignment = vec![usize::MAX; vecs.len()];
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (i, v) in vecs.iter().enumerate() {
+            let best = closest(v, &centroids).0;
+            if assignment[i] != best {
+                assignment[i] = best;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+
+        let dims = vecs[0].len();
+        let mut sums = vec![vec![0.0f32; dims]; k];
+        let mut sizes = vec![0usize; k];
+        for (v, &c) in vecs.iter().zip(&assignment) {
+            sizes[c] += 1;
+            sums[c].iter_mut().zip(v.iter()).for_each(|(s, x)| *s += x);
+        }
+        for c in 0..k {
+            if sizes[c] == 0 {
+                // Re-seed an empty cluster with the point worst served by its centre.
+                let worst = (0..vecs.len())
+                    .min_by(|&a, &b| {
+                        let sa = dot(vecs[a], &centroids[assignment[a]]);
+                        let sb = dot(vecs[b], &centroids[assignment[b]]);
+                        sa.partial_cmp(&sb).unwrap_or(std::cmp::Ordering::Equal)
+                    })
+                    .unwrap_or(0);
+                sums[c] = vecs[worst].to_vec();
+            }
+            normalize(&mut sums[c]);
+        }
+        centroids = sums;
+    }
+    (assignment, centroids)
+}
+
+fn closest(v: &[f32], centroids: &[Vec<f32>]) -> (usize, f32) {
+    centroids
+        .iter()
+        .enumerate()
+        .map(|(c, centre)| (c, dot(v, centre)))
+        .fold((0, f32::MIN), |best, cur| if cur.1 > best.1 { cur } else { best })
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Label clusters with the title words most specific to them (class-based
+/// TF-IDF: frequent in this cluster, rare across clusters).
+fn cluster_keywords<'a>(
+    groups: &[Vec<(usize, f32)>],
+    title: impl Fn(usize) -> &'a str,
+) -> Vec<Vec<String>> {
+    let term_counts: Vec<HashMap<String, usize>> = groups
+        .iter()
+        .map(|g| {
+            let mut counts = HashMap::new();
+            for &(i, _) in g {
+                // Count each word once per title so one repetitive title can't dominate.
+                for word in title_words(title(i)) {
+                    *counts.entry(word).or_default() += 1;
+                }
+            }
+            counts
+        })
+        .collect();
+
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    for counts in &term_counts {
+        for term in counts.keys() {
+            *document_frequency.entry(term.as_str()).or_default() += 1;
+        }
+    }
+
+    let clusters = groups.len() as f32;
+    term_counts
+        .iter()
+        .map(|counts| {
+            let mut scored: Vec<(&String, f32)> = counts
+                .iter()
+                .map(|(term, &count)| {
+                    let df = document_frequency[term.as_str()] as f32;
+                    (term, count as f32 * (1.0 + clusters / df).ln())
+                })
+                .collect();
+            scored.sort_by(|a, b| {
+                b.1.partial_cmp(&a.1)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then_with(|| a.0.cmp(b.0))
+            });
+            scored.into_iter().take(LABEL_SIZE).map(|(t, _)| t.clone()).collect()
+        })
+        .collect()
+}
+
+/// Distinct lowercase words of a title, minus stopwords, numbers and short words.
+fn title_words(title: &str) -> HashSet<String> {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|w| w.chars().count() >= 3)
+        .filter(|w| !w.chars().all(|c| c.is_ascii_digit()))
+        .filter(|w| !STOPWORDS.contains(&w.as_str()))
+        .collect()
+}
+
+/// Small deterministic PRNG for k-means++ seeding.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1).
+    fn unit(&mut self) -> f32 {
+        (self.next() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Note;
+
+    /// A point near `axis`, nudged towards the next one by `offset`.
+    fn vector(axis: usize, offset: f32) -> Vec<f32> {
+        let mut v = vec![0.0; 4];
+        v[axis] = 1.0;
+        v[axis + 1] = offset;
+        v
+    }
+
+    /// Note vectors, and the notes they belong to.
+    type Vault = (Vec<(String, Vec<f32>)>, HashMap<String, NoteMetadata>);
+
+    fn vault() -> Vault {
+        let notes = [
+            ("r1", "Rust borrow checker", "dev", vector(0, 0.1)),
+            ("r2", "Rust lifetimes guide", "dev", vector(0, 0.2)),
+            ("r3", "Rust async runtime", "cooking", vector(0, 0.0)),
+            ("b1", "Sourdough bread recipe", "cooking", vector(2, 0.1)),
+            ("b2", "Bread baking schedule", "cooking", vector(2, 0.0)),
+            ("b3", "Pizza dough recipe", "cooking", vector(2, 0.2)),
+        ];
+        let vectors = notes.iter().map(|n| (n.0.to_string(), n.3.clone())).collect();
+        let metadata = notes
+            .iter()
+            .map(|&(id, title, parent_id, _)| {
+                let note = Note {
+                    id: id.to_string(),
+                    title: title.to_string(),
+                    parent_id: parent_id.to_string(),
+                    ..Default::default()
+                };
+                (id.to_string(), note.metadata())
+            })
+            .collect();
+        (vectors, metadata)
+    }
+
+    fn notebooks() -> HashMap<String, String> {
+        ["dev", "cooking"].map(|id| (id.to_string(), id.to_uppercase())).into()
+    }
+
+    fn member_ids(cluster: &TopicCluster) -> Vec<&str> {
+        let mut ids: Vec<&str> = cluster.members.iter().map(|m| m.note_id.as_str()).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn clusters_are_labelled_by_their_own_title_words() {
+        let (vectors, notes) = vault();
+        let clusters = build_topic_map(vectors, &notes, &notebooks(), Some(2));
+        assert_eq!(clusters.len(), 2);
+        let (rust, bread) = if member_ids(&clusters[0])[0] == "b1" {
+            (&clusters[1], &clusters[0])
+        } else {
+            (&clusters[0], &clusters[1])
+        };
+
+        assert_eq!(member_ids(rust), ["r1", "r2", "r3"]);
+        assert_eq!(rust.keywords[0], "rust");
+        // Two thirds filed in DEV, so the one elsewhere is flagged.
+        assert_eq!(rust.top_notebook.as_deref(), Some("DEV"));
+        let flagged: Vec<&str> = rust
+            .members
+            .iter()
+            .filter(|m| m.outside_top_notebook)
+            .map(|m| m.note_id.as_str())
+            .collect();
+        assert_eq!(flagged, ["r3"]);
+
+        assert_eq!(member_ids(bread), ["b1", "b2", "b3"]);
+        // Tied counts are ordered alphabetically.
+        assert_eq!(bread.keywords[..2], ["bread", "recipe"]);
+        assert!(!bread.members.iter().any(|m| m.outside_top_notebook));
+    }
+
+    #[test]
+    fn clustering_ignores_input_order_and_unknown_notes() {
+        let (mut vectors, notes) = vault();
+        let first = build_topic_map(vectors.clone(), &notes, &notebooks(), Some(2));
+        vectors.reverse();
+        vectors.push(("gone".to_string(), vector(1, 0.0)));
+        let second = build_topic_map(vectors, &notes, &notebooks(), Some(2));
+
+        let ids = |clusters: &[TopicCluster]| -> Vec<Vec<String>> {
+            clusters
+                .iter()
+                .map(|c| member_ids(c).into_iter().map(String::from).collect())
+                .collect()
+        };
+        assert_eq!(ids(&first), ids(&second));
+    }
+
+    #[test]
+    fn title_words_skip_stopwords_numbers_and_short_words() {
+        let mut words: Vec<String> =
+            title_words("The 2024 plan: an Update for the Rust team").into_iter().collect();
+        words.sort();
+        assert_eq!(words, ["plan", "rust", "team", "update"]);
+    }
+}
@@ -1,34 +1,529 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Note {
     pub id: String, // Joplin uses string UUIDs
     pub title: String,
     pub body: String,
     pub updated_time: i64, // Unix timestamp in ms
+    pub is_todo: bool,
+    pub todo_completed: i64, // Unix ms when completed, 0 if open (or not a to-do)
+    pub is_conflict: bool,   // sync conflict copy
+    pub in_trash: bool,
+    pub created_time: i64,      // Unix ms
+    pub user_updated_time: i64, // Unix ms, last edit by the user (excludes sync-only changes)
+    pub source_url: String,     // page a web-clipped note came from, "" if none
+    pub author: String,
+    pub latitude: f64,          // 0.0 if no location was recorded
+    pub longitude: f64,
+    pub markup_language: i64,   // 1 = Markdown, 2 = HTML
+    pub parent_id: String,      // notebook (folder) UUID
+}
+
+impl Note {
+    /// Cache entry for this note (everything except the body).
+    pub fn metadata(&self) -> NoteMetadata {
+        NoteMetadata {
+            id: self.id.clone(),
+            title: self.title.clone(),
+            updated_time: self.updated_time,
+            is_todo: self.is_todo,
+            todo_completed: self.todo_completed,
+            is_conflict: self.is_conflict,
+            in_trash: self.in_trash,
+            created_time: self.created_time,
+            user_updated_time: self.user_updated_time,
+            source_url: self.source_url.clone(),
+            author: self.author.clone(),
+            latitude: self.latitude,
+            longitude: self.longitude,
+            parent_id: self.parent_id.clone(),
+        }
+    }
 }
 
 /// Lightweight note metadata kept in the in-memory cache.
 /// Body is not stored to avoid holding all note content in RAM.
+/// The flags let the UI badge to-dos, conflict copies and trashed notes.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NoteMetadata {
     pub id: String,
     pub title: String,
     pub updated_time: i64,
+    pub is_todo: bool,
+    pub todo_completed: i64,
+    pub is_conflict: bool,
+    pub in_trash: bool,
+    pub created_time: i64,
+    pub user_updated_time: i64,
+    pub source_url: String,
+    pub author: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub parent_id: String,
+}
+
+/// A past version of a note kept in the revision index.
+/// Persisted next to `revisions.bin` so the index can be reused across launches.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevisionMetadata {
+    pub id: String, // revision UUID
+    pub note_id: String,
+    pub title: String,     // title as it was in this revision
+    pub revision_time: i64, // Unix ms
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchResult {
     pub note: NoteMetadata,
     pub score: f32,
+    /// Set when the match is text from a past revision of the note
+    /// rather than its current content.
+    pub revision: Option<RevisionMetadata>,
+    /// How the ranking signals changed the score. Only set when at least
+    /// one signal has a non-zero weight.
+    pub ranking: Option<RankingBreakdown>,
+    /// How the score and rank came about. Only set when `SearchOptions::explain` is on.
+    pub explanation: Option<ScoreExplanation>,
+}
+
+/// Debugging detail for one search result (`SearchOptions::explain`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScoreExplanation {
+    /// "note" for a match on the current text, "revision" for a past version.
+    pub source: String,
+    /// Raw cosine distance reported by the HNSW index (similarity = 1 − distance).
+    pub cosine_distance: f32,
+    /// Paragraph of the note most similar to the query, and its similarity.
+    /// None for revision matches or if the note could not be read.
+    pub matched_passage: Option<String>,
+    pub passage_similarity: Option<f32>,
+    /// Share of the query's words that appear in the note text. Diagnostic
+    /// only; ranking is purely semantic.
+    pub lexical_score: Option<f32>,
+    /// Score changes after similarity, in the order they were applied.
+    pub boosts: Vec<ScoreBoost>,
+    /// Filters the result passed, e.g. "min_score >= 0.30" or "author ~ alice".
+    pub filters: Vec<String>,
+    /// 1-based position among the raw index hits, after filtering
+    /// (tombstones, metadata filters, `MIN_SCORE`, dedup), and in the final list.
+    pub rank_raw: usize,
+    pub rank_filtered: usize,
+    pub rank_final: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreBoost {
+    pub signal: String, // "recency", "usage", "pinned", "linked"
+    pub value: f32,
+}
+
+/// Contribution of each ranking signal to a result's final `score`
+/// (already multiplied by its weight).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RankingBreakdown {
+    pub similarity: f32, // cosine similarity to the query
+    pub recency: f32,
+    pub usage: f32,
+    pub pinned: f32,
+}
+
+/// Optional metadata filters and ordering for `search_notes`.
+/// All filters are ANDed; unset ones match everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    /// Only notes clipped from this domain or its subdomains, e.g. "github.com".
+    pub source_domain: Option<String>,
+    /// Case-insensitive substring of the note's author.
+    pub author: Option<String>,
+    /// `created_time` range in Unix ms, inclusive.
+    pub created_after: Option<i64>,
+    pub created_before: Option<i64>,
+    /// `user_updated_time` range in Unix ms, inclusive.
+    pub updated_after: Option<i64>,
+    pub updated_before: Option<i64>,
+    /// Only notes with (true) or without (false) a recorded location.
+    pub has_location: Option<bool>,
+    pub sort_by: SortBy,
+    /// Oldest first when sorting by a date (ignored for relevance).
+    pub ascending: bool,
+    /// Attach a `ScoreExplanation` to every result. Slower: the notes are
+    /// re-read and split into passages to find the best-matching one.
+    pub explain: bool,
+    /// Diversify results with Maximal Marginal Relevance. 1.0 ranks by
+    /// relevance alone; lower values trade relevance for variety (0.5–0.7
+    /// works well). None leaves the ranking as is.
+    pub mmr_lambda: Option<f32>,
+}
+
+/// Input of `search_by_example`. At least one positive example is required.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchExample {
+    /// Text to find similar notes to. Any length; long text is chunked.
+    pub text: Option<String>,
+    /// Notes whose combined topic to search for.
+    pub note_ids: Vec<String>,
+    /// Text and notes describing what results should *not* be like.
+    pub negative_text: Option<String>,
+    pub negative_note_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortBy {
+    #[default]
+    Relevance,
+    CreatedTime,
+    UpdatedTime,
+}
+
+/// What the indexer is doing, reported in `IndexStatus::phase`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexPhase {
+    /// Looking for the Joplin database.
+    #[default]
+    Detecting,
+    /// Downloading (first run) or loading the embedding model.
+    DownloadingModel,
+    /// Loading the saved index and the note list.
+    LoadingIndex,
+    /// Embedding notes for a full build.
+    Embedding,
+    /// Writing the built index to disk.
+    Saving,
+    Ready,
+    /// The last build failed or was cancelled; see `IndexStatus::error`.
+    Error,
+}
+
+/// Payload of the `index-status` event.
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct IndexStatus {
+    pub phase: IndexPhase,
     pub total_notes: usize,
     pub indexed_notes: usize,
+    /// Searches can run. Stays true while a rebuild replaces a loaded index.
     pub is_ready: bool,
-    pub is_downloading_model: bool,
-    pub download_progress: f32, // 0.0 to 1.0
+    /// Progress of the current phase, 0.0 to 1.0. None if it can't be measured.
+    pub progress: Option<f32>,
+    /// Embedding throughput of the running build.
+    pub notes_per_sec: Option<f32>,
+    /// Estimated seconds until the current phase is done.
+    pub eta_secs: Option<u64>,
+    /// Model bytes downloaded so far, and the size of the files started so far.
+    pub downloaded_bytes: u64,
+    pub download_total_bytes: Option<u64>,
+    pub error: Option<AppError>,
+}
+
+impl IndexStatus {
+    /// Move to `phase`, dropping the progress figures of the previous one.
+    pub fn enter(&mut self, phase: IndexPhase) {
+        self.phase = phase;
+        self.progress = None;
+        self.notes_per_sec = None;
+        self.eta_secs = None;
+    }
+
+    /// Move to `IndexPhase::Error` with `error`.
+    pub fn fail(&mut self, error: AppError) {
+        self.enter(IndexPhase::Error);
+        self.error = Some(error);
+    }
+}
+
+/// A query the user ran, kept in the search history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub query: String,
+    pub options: SearchOptions,
+    pub time: i64, // Unix ms
+}
+
+/// A named query with its filters, kept until the user deletes it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub id: String,
+    pub name: String,
+    pub query: String,
+    pub options: SearchOptions,
+    pub created_time: i64, // Unix ms
+    /// Alert when a new or edited note reaches this score. None: no alerts.
+    #[serde(default)]
+    pub alert_threshold: Option<f32>,
+    /// Also show a desktop notification for alerts.
+    #[serde(default)]
+    pub notify: bool,
+}
+
+/// Payload of the `saved-search-alert` event: notes that newly match a saved search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSearchAlert {
+    pub search_id: String,
+    pub name: String,
+    pub query: String,
+    /// Best first.
+    pub matches: Vec<SearchResult>,
+}
+
+/// Tags and notebooks proposed for a note from its nearest neighbours.
+/// Suggestions are informational only; nothing is written to Joplin.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteSuggestion {
+    pub note_id: String,
+    pub title: String,
+    pub tags: Vec<TagSuggestion>,           // best first, excludes tags the note already has
+    pub notebooks: Vec<NotebookSuggestion>, // best first, may include the current notebook
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagSuggestion {
+    pub tag: String,
+    pub confidence: f32, // share of the similarity-weighted tagged neighbours with this tag
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotebookSuggestion {
+    pub notebook_id: String,
+    pub notebook: String, // notebook path
+    pub confidence: f32,  // share of the similarity-weighted neighbours filed there
+}
+
+/// A set of notes that look like copies of each other.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub notes: Vec<DuplicateNote>, // most recently updated first
+    pub pairs: Vec<DuplicatePair>, // the matches that linked the group, best first
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateNote {
+    pub note: NoteMetadata,
+    pub notebook: String, // notebook path, "" if unknown
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicatePair {
+    pub a: String, // note UUIDs
+    pub b: String,
+    pub similarity: f32,              // cosine similarity of the embeddings
+    pub text_similarity: Option<f32>, // shingle overlap (Jaccard), when the text check ran
+}
+
+/// Parameters for `find_duplicates`. Missing fields use the defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DuplicateOptions {
+    /// Minimum embedding similarity for two notes to count as duplicates.
+    pub threshold: f32,
+    /// Also compare the note texts word by word and drop pairs below `min_text_similarity`.
+    pub text_check: bool,
+    pub min_text_similarity: f32,
+}
+
+impl Default for DuplicateOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 0.95,
+            text_check: false,
+            min_text_similarity: 0.5,
+        }
+    }
+}
+
+/// One topic found by clustering the vault's embeddings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopicCluster {
+    pub id: usize,
+    pub keywords: Vec<String>,              // most distinctive title words, best first
+    pub representative_titles: Vec<String>, // titles of the notes closest to the centre
+    pub top_notebook: Option<String>,       // notebook path holding most members
+    pub members: Vec<TopicMember>,          // closest to the centre first
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopicMember {
+    pub note_id: String,
+    pub title: String,
+    pub notebook: String,        // notebook path, "" if unknown
+    pub similarity: f32,         // cosine similarity to the cluster centre
+    pub outside_top_notebook: bool, // possibly misfiled: topic lives mostly elsewhere
+}
+
+/// A Joplin database found during auto-detection.
+/// One entry per profile per install (native, Flatpak, Snap, …).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DbCandidate {
+    pub path: String,
+    pub profile_id: String,
+    pub profile_name: String,
+    pub install: String,             // "Joplin", "Flatpak", "Snap", …
+    pub is_current_profile: bool,    // profile Joplin was last opened with
+    pub note_count: Option<usize>,   // None if the DB could not be read
+    pub modified_time: Option<i64>,  // Unix ms, newest of DB and WAL file
+}
+
+/// Payload of the `watcher-status` event: how the Joplin database is being watched.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatcherStatus {
+    pub mode: String, // "stopped", "events" (filesystem notifications), "polling"
+    pub debounce_ms: u64,
+    /// A change was seen and the update is waiting out the debounce.
+    pub pending: bool,
+    pub last_change: Option<i64>, // Unix ms
+    pub last_update: Option<i64>, // Unix ms, when the last delta update started
+    /// Why notifications couldn't be used, when polling was not requested.
     pub error: Option<String>,
 }
+
+impl Default for WatcherStatus {
+    fn default() -> Self {
+        Self {
+            mode: "stopped".to_string(),
+            debounce_ms: 0,
+            pending: false,
+            last_change: None,
+            last_update: None,
+            error: None,
+        }
+    }
+}
+
+/// User-configurable options, persisted in `settings.json` next to `index.bin`.
+/// Missing fields fall back to their defaults so older files keep loading.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Joplin database chosen with `set_joplin_db_path`; preferred over
+    /// detection at startup while the file still exists.
+    pub db_path: Option<String>,
+    /// Reconstruct past note revisions and make them searchable (opt-in).
+    pub index_revisions: bool,
+    /// Index notes in Joplin's trash (labelled in results).
+    pub include_trash: bool,
+    /// Index sync conflict copies (labelled in results).
+    pub include_conflicts: bool,
+    /// Index to-dos that have no body, by title alone.
+    pub include_empty_todos: bool,
+    /// Nudge up results that are linked from other strong results.
+    pub boost_linked_results: bool,
+    /// Extra ranking signals on top of similarity. All off by default.
+    pub ranking: RankingWeights,
+    /// Don't record typed queries (saved searches are unaffected).
+    pub disable_history: bool,
+    /// Quiet period after the last database write before indexing changes.
+    /// None: `watcher::DEFAULT_DEBOUNCE`.
+    pub watcher_debounce_ms: Option<u64>,
+    /// Poll file modification times instead of using filesystem notifications.
+    /// Needed for databases on network filesystems, which don't report changes.
+    pub watcher_polling: bool,
+    /// When and how the index is compacted or rebuilt.
+    pub maintenance: MaintenanceSettings,
+    /// Inference worker threads, each with its own copy of the model (~130 MB).
+    /// More than one lets searches run while indexing. None:
+    /// `embeddings::DEFAULT_WORKERS`.
+    pub inference_workers: Option<usize>,
+    /// Limits on the CPU used by inference.
+    pub resources: ResourceLimits,
+}
+
+/// How much of the machine inference may use.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResourceLimits {
+    /// CPU cores the background inference workers may run on. None: all of
+    /// them. Linux only.
+    pub max_threads: Option<usize>,
+    /// Notes embedded per step of full, delta and revision indexing.
+    /// Smaller batches use less memory and leave the CPU free more often.
+    /// Each step is still split into `inference_slice` jobs.
+    pub batch_size: usize,
+    /// Texts per background inference job. A search waits behind at most
+    /// one job, so smaller slices keep searches quicker during indexing.
+    pub inference_slice: usize,
+    /// Run background inference at a lower scheduling priority (nice 10), so
+    /// other programs win when the machine is busy. Linux only.
+    pub low_priority: bool,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            max_threads: None,
+            batch_size: 64,
+            inference_slice: 16,
+            low_priority: false,
+        }
+    }
+}
+
+/// Settings of the background index maintenance scheduler.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceSettings {
+    /// Don't start any maintenance (a running task still finishes).
+    pub paused: bool,
+    /// Minutes without searches or note changes before maintenance may run.
+    pub idle_minutes: u32,
+    /// Compact the index once this share of its graph nodes is dead
+    /// (left behind by deleted or re-embedded notes).
+    pub compact_dead_ratio: f32,
+}
+
+impl Default for MaintenanceSettings {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            idle_minutes: 5,
+            compact_dead_ratio: 0.25,
+        }
+    }
+}
+
+/// Payload of the `maintenance-status` event.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaintenanceStatus {
+    /// What the index needs, waiting for the app to be idle:
+    /// "compact", "reembed" (notes from an older Joplin schema) or "rebuild"
+    /// (different embedding model). None if it is healthy.
+    pub needed: Option<String>,
+    /// The task running now, if any.
+    pub running: Option<String>,
+    pub last_task: Option<String>,
+    pub last_run: Option<i64>, // Unix ms
+    pub live_nodes: usize,
+    pub dead_nodes: usize,
+}
+
+/// Weights of the ranking signals added to the similarity score. A weight of
+/// 0 disables the signal. Each signal is in [0, 1] before weighting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RankingWeights {
+    /// Time decay on the note's last edit: 1 when edited now, ½ after one half-life.
+    pub recency: f32,
+    pub recency_half_life_days: f32,
+    /// Recent opens via the app (selecting a result or "Open in Joplin"),
+    /// each decaying with its own half-life, capped at 1.
+    pub usage: f32,
+    pub usage_half_life_days: f32,
+    /// Flat boost for pinned notes.
+    pub pinned: f32,
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        Self {
+            recency: 0.0,
+            recency_half_life_days: 90.0,
+            usage: 0.0,
+            usage_half_life_days: 14.0,
+            pinned: 0.0,
+        }
+    }
+}
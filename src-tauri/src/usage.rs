@@ -0,0 +1,45 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Open timestamps kept per note; older ones add almost nothing to the usage signal.
+const MAX_OPENS_PER_NOTE: usize = 10;
+
+/// Which notes the user opens and pins, persisted in `usage.json` next to
+/// `index.bin`. Feeds the usage and pin ranking signals.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageData {
+    /// Note UUID → recent open times (Unix ms), newest last.
+    pub opens: HashMap<String, Vec<i64>>,
+    pub pinned: HashSet<String>,
+}
+
+impl UsageData {
+    pub fn record_open(&mut self, note_id: &str, now_ms: i64) {
+        let opens = self.opens.entry(note_id.to_string()).or_default();
+        opens.push(now_ms);
+        if opens.len() > MAX_OPENS_PER_NOTE {
+            opens.remove(0);
+        }
+    }
+}
+
+/// Current time in Unix ms.
+pub fn now_ms() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Load usage data from disk. Starts empty if the file is missing or unreadable.
+pub fn load(path: &Path) -> UsageData {
+    crate::storage::load_json(path).unwrap_or_default()
+}
+
+/// Persist usage data atomically (write temp file, then rename).
+pub fn save(usage: &UsageData, path: &Path) -> Result<()> {
+    crate::storage::save_json_atomic(usage, path)
+}
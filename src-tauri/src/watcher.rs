@@ -1,75 +1,314 @@
+use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime};
-use tauri::Manager;
+use tauri::{Emitter, Manager};
+use tokio::sync::mpsc;
 
+use crate::error::AppError;
 use crate::AppMutex;
 
-/// How often to check if the Joplin SQLite file has changed.
+/// How often to check the Joplin SQLite file's mtime when polling.
 const POLL_INTERVAL: Duration = Duration::from_secs(10);
 
-/// Minimum quiet period after a change before triggering an update.
+/// Default quiet period after a change before triggering an update.
 /// Joplin may write to SQLite multiple times during a save; we debounce.
-const DEBOUNCE: Duration = Duration::from_secs(5);
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(1500);
 
-/// Start the background file watcher. Polls the Joplin SQLite file for
-/// modifications and triggers an incremental index update when changes detected.
+/// Start the background file watcher. Watches the Joplin SQLite file (and its
+/// WAL) and triggers an incremental index update when it changes. Uses
+/// filesystem notifications, falling back to polling if they are unavailable
+/// or `Settings::watcher_polling` is on. Safe to call again: a running
+/// watcher just picks up the new database path and settings.
 pub async fn start_watcher(app: tauri::AppHandle) {
+    {
+        let state = app.state::<AppMutex>();
+        let mut s = state.lock().await;
+        if s.is_watching {
+            s.watcher_wakeup.notify_one();
+            return;
+        }
+        s.is_watching = true;
+    }
     tauri::async_runtime::spawn(async move {
         watch_loop(app).await;
     });
 }
 
-async fn watch_loop(app: tauri::AppHandle) {
-    let mut last_modified: Option<SystemTime> = None;
-    let mut pending_since: Option<SystemTime> = None;
+/// Make a running watcher re-read the database path and watcher settings.
+pub async fn reconfigure(app: &tauri::AppHandle) {
+    let state = app.state::<AppMutex>();
+    state.lock().await.watcher_wakeup.notify_one();
+}
 
-    loop {
-        tokio::time::sleep(POLL_INTERVAL).await;
+/// Why a watch pass returned.
+enum Exit {
+    /// Database path or settings changed; start over.
+    Reconfigure,
+    /// Notifications stopped working; fall back to polling.
+    Failed(String),
+}
 
-        let db_path = {
+async fn watch_loop(app: tauri::AppHandle) {
+    let mut fallback_error: Option<String> = None;
+    loop {
+        let (db_path, debounce, polling, wakeup) = {
             let state = app.state::<AppMutex>();
-            let x = state.lock().await.db_path.clone();
-            x
+            let s = state.lock().await;
+            (
+                s.db_path.clone(),
+                s.settings
+                    .watcher_debounce_ms
+                    .map(Duration::from_millis)
+                    .unwrap_or(DEFAULT_DEBOUNCE),
+                s.settings.watcher_polling,
+                s.watcher_wakeup.clone(),
+            )
         };
-
-        let db_path = match db_path {
-            Some(p) => p,
-            None => continue, // no DB configured yet
+        let Some(db_path) = db_path else {
+            // No DB configured yet; wait until one is.
+            set_mode(&app, "stopped", debounce, None).await;
+            wakeup.notified().await;
+            continue;
         };
+        let db_path = PathBuf::from(db_path);
 
-        // Check modification time of the main DB file AND the WAL file.
-        // Joplin uses SQLite WAL mode: writes go to database.sqlite-wal first
-        // and the main file's mtime only changes after a WAL checkpoint.
-        // Watching only the main file misses most note saves.
-        let mtime_main = std::fs::metadata(&db_path).and_then(|m| m.modified()).ok();
-        let mtime_wal  = std::fs::metadata(format!("{}-wal", &db_path)).and_then(|m| m.modified()).ok();
-        let current_mtime = match (mtime_main, mtime_wal) {
-            (Some(a), Some(b)) => Some(a.max(b)),
-            (a, b)             => a.or(b),
+        let exit = if polling || fallback_error.is_some() {
+            set_mode(&app, "polling", debounce, fallback_error.clone()).await;
+            poll(&app, &db_path, debounce, &wakeup).await
+        } else {
+            match watch_events(&db_path) {
+                Ok((watcher, events)) => {
+                    set_mode(&app, "events", debounce, None).await;
+                    let exit = listen(&app, &db_path, events, debounce, &wakeup).await;
+                    drop(watcher);
+                    exit
+                }
+                Err(e) => Exit::Failed(e.to_string()),
+            }
         };
-
-        let changed = match (last_modified, current_mtime) {
-            (None, Some(t)) => {
-                last_modified = Some(t);
-                false // first observation — don't treat as a change
+        match exit {
+            // A new path or setting may make notifications usable again.
+            Exit::Reconfigure => fallback_error = None,
+            Exit::Failed(error) => {
+                crate::error::record(&app, "watcher", AppError::Watcher(error.clone())).await;
+                fallback_error = Some(error);
             }
-            (Some(prev), Some(cur)) if cur != prev => {
-                last_modified = Some(cur);
-                true
+        }
+    }
+}
+
+/// Subscribe to filesystem notifications for the directory holding the DB.
+/// The directory is watched rather than the files because SQLite creates and
+/// deletes the WAL file as it checkpoints.
+fn watch_events(
+    db_path: &Path,
+) -> notify::Result<(
+    notify::RecommendedWatcher,
+    mpsc::UnboundedReceiver<notify::Result<notify::Event>>,
+)> {
+    use notify::Watcher;
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })?;
+    let dir = db_path.parent().unwrap_or(Path::new("."));
+    watcher.watch(dir, notify::RecursiveMode::NonRecursive)?;
+    Ok((watcher, rx))
+}
+
+/// Whether an event is a write to the DB or its WAL. Reads (including our own),
+/// metadata-only changes and the WAL being created or removed are ignored:
+/// SQLite does that when any connection opens or closes, ours too.
+fn is_db_write(event: &notify::Event, db_path: &Path) -> bool {
+    use notify::event::{EventKind, ModifyKind};
+
+    let wal = wal_path(db_path);
+    let is_write = matches!(
+        event.kind,
+        EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Any)
+    );
+    is_write && event.paths.iter().any(|p| p == db_path || *p == wal)
+}
+
+/// Wait for DB writes, debounce them, and run a delta update per burst.
+async fn listen(
+    app: &tauri::AppHandle,
+    db_path: &Path,
+    mut events: mpsc::UnboundedReceiver<notify::Result<notify::Event>>,
+    debounce: Duration,
+    wakeup: &tokio::sync::Notify,
+) -> Exit {
+    let mut last_modified = modified_time(db_path);
+    loop {
+        // Idle: sleep until something happens.
+        tokio::select! {
+            event = events.recv() => match event {
+                Some(Ok(event)) if is_db_write(&event, db_path) => {}
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Exit::Failed(e.to_string()),
+                None => return Exit::Failed("watcher_closed".to_string()),
+            },
+            _ = wakeup.notified() => return Exit::Reconfigure,
+        }
+
+        // Debounce: wait until the DB has been quiet for `debounce`.
+        set_pending(app, true).await;
+        let mut deadline = tokio::time::Instant::now() + debounce;
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Some(Ok(event)) if is_db_write(&event, db_path) => {
+                        deadline = tokio::time::Instant::now() + debounce;
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Exit::Failed(e.to_string()),
+                    None => return Exit::Failed("watcher_closed".to_string()),
+                },
+                _ = tokio::time::sleep_until(deadline) => break,
+                _ = wakeup.notified() => return Exit::Reconfigure,
             }
-            _ => false,
+        }
+
+        // Skip bursts that didn't change the files (e.g. a WAL created and
+        // removed again without a write).
+        let modified = modified_time(db_path);
+        if modified != last_modified {
+            last_modified = modified;
+            run_update(app, db_path).await;
+        } else {
+            set_pending(app, false).await;
+        }
+    }
+}
+
+/// Fallback: compare mtimes every `POLL_INTERVAL`, then debounce.
+async fn poll(
+    app: &tauri::AppHandle,
+    db_path: &Path,
+    debounce: Duration,
+    wakeup: &tokio::sync::Notify,
+) -> Exit {
+    let mut last_modified = modified_time(db_path);
+    let mut pending_since: Option<SystemTime> = None;
+
+    loop {
+        // While a change is pending, check back in time for the debounce.
+        let interval = if pending_since.is_some() {
+            debounce.min(POLL_INTERVAL)
+        } else {
+            POLL_INTERVAL
         };
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = wakeup.notified() => return Exit::Reconfigure,
+        }
 
-        if changed {
-            // Start (or reset) the debounce timer
-            pending_since = Some(SystemTime::now());
+        let current = modified_time(db_path);
+        if current.is_some() && current != last_modified {
+            let first_observation = last_modified.is_none();
+            last_modified = current;
+            if !first_observation {
+                // Start (or reset) the debounce timer
+                pending_since = Some(SystemTime::now());
+                set_pending(app, true).await;
+                continue;
+            }
         }
 
         // Fire update if we've been waiting long enough
         if let Some(since) = pending_since {
-            if since.elapsed().unwrap_or_default() >= DEBOUNCE {
+            if since.elapsed().unwrap_or_default() >= debounce {
                 pending_since = None;
-                crate::commands::run_delta_update(app.clone(), db_path).await;
+                run_update(app, db_path).await;
             }
         }
     }
 }
+
+async fn run_update(app: &tauri::AppHandle, db_path: &Path) {
+    update_status(app, |status| {
+        status.pending = false;
+        status.last_update = Some(crate::usage::now_ms());
+    })
+    .await;
+    crate::commands::run_delta_update(app.clone(), db_path.to_string_lossy().into_owned()).await;
+}
+
+/// Newer mtime of the main DB file and the WAL file.
+/// Joplin uses SQLite WAL mode: writes go to database.sqlite-wal first
+/// and the main file's mtime only changes after a WAL checkpoint.
+/// Watching only the main file misses most note saves.
+fn modified_time(db_path: &Path) -> Option<SystemTime> {
+    let mtime_main = std::fs::metadata(db_path).and_then(|m| m.modified()).ok();
+    let mtime_wal = std::fs::metadata(wal_path(db_path))
+        .and_then(|m| m.modified())
+        .ok();
+    match (mtime_main, mtime_wal) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn wal_path(db_path: &Path) -> PathBuf {
+    let mut wal = db_path.as_os_str().to_owned();
+    wal.push("-wal");
+    PathBuf::from(wal)
+}
+
+async fn set_mode(app: &tauri::AppHandle, mode: &str, debounce: Duration, error: Option<String>) {
+    update_status(app, |status| {
+        status.mode = mode.to_string();
+        status.debounce_ms = debounce.as_millis() as u64;
+        status.pending = false;
+        status.error = error;
+    })
+    .await;
+}
+
+async fn set_pending(app: &tauri::AppHandle, pending: bool) {
+    update_status(app, |status| {
+        status.pending = pending;
+        if pending {
+            status.last_change = Some(crate::usage::now_ms());
+        }
+    })
+    .await;
+}
+
+/// Change the watcher status and emit a `watcher-status` event.
+async fn update_status(
+    app: &tauri::AppHandle,
+    change: impl FnOnce(&mut crate::types::WatcherStatus),
+) {
+    let state = app.state::<AppMutex>();
+    let mut s = state.lock().await;
+    change(&mut s.watcher_status);
+    let _ = app.emit("watcher-status", &s.watcher_status);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, EventKind, MetadataKind, ModifyKind, RemoveKind};
+
+    fn event(kind: EventKind, path: &Path) -> notify::Event {
+        notify::Event::new(kind).add_path(path.to_path_buf())
+    }
+
+    #[test]
+    fn only_data_changes_to_the_db_or_wal_are_writes() {
+        let db = Path::new("/joplin/database.sqlite");
+        let wal = wal_path(db);
+        let data = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        assert!(is_db_write(&event(data, db), db));
+        assert!(is_db_write(&event(data, &wal), db));
+        assert!(is_db_write(&event(EventKind::Modify(ModifyKind::Any), &wal), db));
+        assert!(!is_db_write(&event(data, Path::new("/joplin/settings.json")), db));
+
+        // Opening or closing a connection creates or removes the WAL.
+        assert!(!is_db_write(&event(EventKind::Create(CreateKind::File), &wal), db));
+        assert!(!is_db_write(&event(EventKind::Remove(RemoveKind::File), &wal), db));
+        let metadata = EventKind::Modify(ModifyKind::Metadata(MetadataKind::AccessTime));
+        assert!(!is_db_write(&event(metadata, db), db));
+    }
+}
@@ -2,7 +2,7 @@ import { invoke } from '@tauri-apps/api/core'
 import { listen } from '@tauri-apps/api/event'
 import { useEffect, useState } from 'react'
 import { useAppStore } from './store'
-import type { IndexStatus } from './types'
+import type { DbCandidate, IndexStatus } from './types'
 import SetupScreen from './components/SetupScreen'
 import IndexingScreen from './components/IndexingScreen'
 import MainLayout from './components/MainLayout'
@@ -10,16 +10,19 @@ import MainLayout from './components/MainLayout'
 export default function App() {
   const { dbPath, indexStatus, setDbPath, setIndexStatus } = useAppStore()
   const [loading, setLoading] = useState(true)
+  const [candidates, setCandidates] = useState<DbCandidate[]>([])
 
   useEffect(() => {
     let unlistenFn: (() => void) | undefined
 
     Promise.all([
-      invoke<string | null>('detect_db_path'),
+      invoke<DbCandidate[]>('detect_db_path'),
       invoke<IndexStatus>('get_index_status'),
     ])
-      .then(([path, status]) => {
-        if (path) setDbPath(path)
+      .then(([found, status]) => {
+        // The backend only auto-selects an unambiguous match
+        if (found.length === 1) setDbPath(found[0].path)
+        else setCandidates(found)
         setIndexStatus(status)
       })
       .catch((err) => console.error('startup invoke failed:', err))
@@ -39,7 +42,7 @@ export default function App() {
   if (loading) return null
 
   if (dbPath === null) {
-    return <SetupScreen />
+    return <SetupScreen candidates={candidates} />
   }
 
   if (!indexStatus.is_ready) {
@@ -1,6 +1,6 @@
-import { useCallback } from 'react'
+import { useCallback, useEffect, useState } from 'react'
 import { invoke } from '@tauri-apps/api/core'
-import type { Note } from '../types'
+import type { Note, NoteMetadata } from '../types'
 import { renderMarkdown } from '../lib/renderMarkdown'
 import { useAppStore } from '../store'
 
@@ -13,6 +13,45 @@ export default function DetailPanel({ note }: { note: Note }) {
 
   const html = note.body.trim() ? renderMarkdown(note.body) : ''
 
+  const [backlinks, setBacklinks] = useState<NoteMetadata[]>([])
+  const [outgoing, setOutgoing] = useState<NoteMetadata[]>([])
+  const [pinned, setPinned] = useState(false)
+
+  useEffect(() => {
+    invoke<string[]>('get_pinned_notes')
+      .then((ids) => setPinned(ids.includes(note.id)))
+      .catch(console.error)
+  }, [note.id])
+
+  const togglePin = () => {
+    invoke('pin_note', { noteId: note.id, pinned: !pinned })
+      .then(() => setPinned(!pinned))
+      .catch(console.error)
+  }
+
+  useEffect(() => {
+    let cancelled = false
+    Promise.all([
+      invoke<NoteMetadata[]>('get_backlinks', { noteId: note.id }),
+      invoke<NoteMetadata[]>('get_outgoing_links', { noteId: note.id }),
+    ])
+      .then(([incoming, outgoingLinks]) => {
+        if (cancelled) return
+        setBacklinks(incoming)
+        setOutgoing(outgoingLinks)
+      })
+      .catch(console.error)
+    return () => {
+      cancelled = true
+    }
+  }, [note.id])
+
+  const openLinked = (id: string) => {
+    invoke<Note>('get_note', { id })
+      .then((fetchedNote) => setSelectedNote(fetchedNote))
+      .catch(console.error)
+  }
+
   const handleOpen = () => {
     if (!canOpen) return
     invoke('open_in_joplin', { noteId: note.id }).catch(console.error)
@@ -45,7 +84,12 @@ export default function DetailPanel({ note }: { note: Note }) {
 
   return (
     <div className="flex h-full flex-col">
-      <h1 className="mb-4 text-2xl font-bold text-zinc-100">{note.title}</h1>
+      <h1 className="mb-1 text-2xl font-bold text-zinc-100">{note.title}</h1>
+      <p className="mb-4 truncate text-xs text-zinc-500">
+        {note.created_time > 0 && `Created ${new Date(note.created_time).toLocaleDateString()}`}
+        {note.author && ` · ${note.author}`}
+        {note.source_url && ` · ${note.source_url}`}
+      </p>
       <div className="mb-6 flex-1 rounded-lg border border-zinc-800 bg-zinc-900/50 p-4 overflow-y-auto">
         {html ? (
           <div
@@ -57,7 +101,33 @@ export default function DetailPanel({ note }: { note: Note }) {
           <p className="text-sm text-zinc-500 italic">No content</p>
         )}
       </div>
-      <div className="mt-auto">
+      {(backlinks.length > 0 || outgoing.length > 0) && (
+        <div className="mb-6 space-y-2 text-xs">
+          {[
+            { label: 'Linked from', notes: backlinks },
+            { label: 'Links to', notes: outgoing },
+          ]
+            .filter(({ notes }) => notes.length > 0)
+            .map(({ label, notes }) => (
+              <p key={label} className="text-zinc-500">
+                {label}:{' '}
+                {notes.map((n, i) => (
+                  <span key={n.id}>
+                    {i > 0 && ', '}
+                    <button
+                      type="button"
+                      onClick={() => openLinked(n.id)}
+                      className="text-indigo-400 hover:underline"
+                    >
+                      {n.title || 'Untitled'}
+                    </button>
+                  </span>
+                ))}
+              </p>
+            ))}
+        </div>
+      )}
+      <div className="mt-auto flex items-center gap-3">
         {canOpen ? (
           <button
             type="button"
@@ -69,6 +139,13 @@ export default function DetailPanel({ note }: { note: Note }) {
         ) : (
           <span className="text-xs text-red-400">Invalid note ID</span>
         )}
+        <button
+          type="button"
+          onClick={togglePin}
+          className="rounded-lg border border-zinc-700 px-4 py-3 text-sm text-zinc-300 hover:bg-zinc-800 focus:outline-none focus:ring-2 focus:ring-indigo-500 transition-colors"
+        >
+          {pinned ? 'Unpin' : 'Pin'}
+        </button>
       </div>
     </div>
   )
@@ -1,42 +1,68 @@
 import { useAppStore } from '../store'
+import type { IndexPhase } from '../types'
+
+const TITLES: Record<IndexPhase, string> = {
+  detecting: 'Preparing…',
+  downloading_model: 'Downloading model…',
+  loading_index: 'Loading index…',
+  embedding: 'Building index…',
+  saving: 'Saving index…',
+  ready: 'Ready',
+  error: 'Indexing failed',
+}
+
+function formatMB(bytes: number): string {
+  return `${(bytes / 1_000_000).toFixed(1)} MB`
+}
+
+function formatEta(secs: number): string {
+  if (secs < 60) return `${secs}s left`
+  const mins = Math.round(secs / 60)
+  return mins < 60 ? `${mins} min left` : `${Math.floor(mins / 60)} h ${mins % 60} min left`
+}
 
 export default function IndexingScreen() {
   const { indexStatus } = useAppStore()
-  const { indexed_notes, total_notes, is_downloading_model, download_progress, error } = indexStatus
+  const {
+    phase,
+    indexed_notes,
+    total_notes,
+    progress,
+    notes_per_sec,
+    eta_secs,
+    downloaded_bytes,
+    download_total_bytes,
+    error,
+  } = indexStatus
 
   if (error) {
     return (
       <div className="flex h-screen w-full flex-col items-center justify-center bg-zinc-950 text-zinc-100">
         <h1 className="mb-2 text-xl font-bold text-red-400">Indexing failed</h1>
-        <p className="max-w-sm text-center text-sm text-zinc-400">{error}</p>
+        <p className="max-w-sm text-center text-sm text-zinc-400">{error.message}</p>
       </div>
     )
   }
 
-  const progressValue = is_downloading_model
-    ? download_progress * 100
-    : total_notes > 0 ? indexed_notes : undefined
-
-  const progressMax = is_downloading_model
-    ? 100
-    : total_notes > 0 ? total_notes : undefined
-
-  const label = is_downloading_model
-    ? `Downloading model… ${Math.round(download_progress * 100)}%`
-    : total_notes > 0
-      ? `${indexed_notes} / ${total_notes} notes indexed`
-      : 'Preparing…'
+  let label: string
+  if (phase === 'downloading_model' && download_total_bytes) {
+    label = `${formatMB(downloaded_bytes)} / ${formatMB(download_total_bytes)}`
+  } else if (phase === 'embedding' && total_notes > 0) {
+    label = `${indexed_notes} / ${total_notes} notes indexed`
+    if (notes_per_sec) label += ` · ${notes_per_sec.toFixed(1)} notes/s`
+  } else {
+    label = 'Preparing…'
+  }
+  if (eta_secs !== null) label += ` · ${formatEta(eta_secs)}`
 
   return (
     <div className="flex h-screen w-full flex-col items-center justify-center bg-zinc-950 text-zinc-100">
-      <h1 className="mb-6 text-xl font-bold">
-        {is_downloading_model ? 'Downloading model…' : 'Building index…'}
-      </h1>
+      <h1 className="mb-6 text-xl font-bold">{TITLES[phase]}</h1>
       <div className="w-64">
         <progress
           className="h-2 w-full rounded-lg bg-zinc-800 accent-indigo-500"
-          value={progressValue}
-          max={progressMax}
+          value={progress !== null ? progress * 100 : undefined}
+          max={100}
         />
         <p className="mt-3 text-sm text-zinc-400">{label}</p>
       </div>
@@ -1,13 +1,15 @@
 import { invoke } from '@tauri-apps/api/core'
 import { useAppStore } from '../store'
-import type { Note } from '../types'
+import type { Note, SearchResult } from '../types'
 
 export default function ResultsList() {
   const { results, selectedNote, setSelectedNote, query } = useAppStore()
 
-  const handleSelect = async (id: string) => {
+  const handleSelect = async (result: SearchResult) => {
     try {
-      const note = await invoke<Note>('get_note', { id })
+      const note = result.revision
+        ? await invoke<Note>('get_note_revision', { revisionId: result.revision.id })
+        : await invoke<Note>('get_note', { id: result.note.id })
       setSelectedNote(note)
     } catch (err) {
       console.error('get_note failed:', err)
@@ -25,15 +27,27 @@ export default function ResultsList() {
         <button
           key={result.note.id}
           type="button"
-          onClick={() => handleSelect(result.note.id)}
+          onClick={() => handleSelect(result)}
           className={`w-full text-left cursor-pointer rounded-lg px-3 py-3 transition-colors ${
             selectedNote?.id === result.note.id
               ? 'bg-indigo-900/30 border-l-2 border-indigo-500'
               : 'hover:bg-zinc-800 border-l-2 border-transparent'
           }`}
         >
-          <h3 className="font-medium text-zinc-100">{result.note.title}</h3>
-          <p className="text-xs text-zinc-400">{(result.score * 100).toFixed(0)}% match</p>
+          <h3 className="font-medium text-zinc-100">
+            {result.note.is_todo && (result.note.todo_completed ? '☑ ' : '☐ ')}
+            {result.note.title}
+          </h3>
+          {(result.note.in_trash || result.note.is_conflict) && (
+            <div className="mt-1 flex gap-1">
+              {result.note.in_trash && <span className="rounded bg-zinc-700 px-1.5 text-[10px] text-zinc-300">Trash</span>}
+              {result.note.is_conflict && <span className="rounded bg-amber-900/60 px-1.5 text-[10px] text-amber-300">Conflict</span>}
+            </div>
+          )}
+          <p className="text-xs text-zinc-400">
+            {(result.score * 100).toFixed(0)}% match
+            {result.revision && ` · earlier version from ${new Date(result.revision.revision_time).toLocaleDateString()}`}
+          </p>
         </button>
       ))}
     </div>
@@ -1,7 +1,7 @@
 import { useEffect, useRef } from 'react'
 import { invoke } from '@tauri-apps/api/core'
 import { useAppStore } from '../store'
-import type { SearchResult } from '../types'
+import type { AppError, SearchResult } from '../types'
 
 export default function SearchBar({ query, setQuery }: { query: string; setQuery: (q: string) => void }) {
   const { setResults } = useAppStore()
@@ -24,7 +24,8 @@ export default function SearchBar({ query, setQuery }: { query: string; setQuery
           setResults(results)
         }
       } catch (error) {
-        if (error === 'index_not_ready' || error === 'model_not_loaded') return
+        const { code } = error as AppError
+        if (code === 'index_not_ready' || code === 'model_not_loaded') return
         console.error('search_notes failed:', error)
       }
     }, 350)
@@ -2,12 +2,18 @@ import { useState } from 'react'
 import { invoke } from '@tauri-apps/api/core'
 import { open } from '@tauri-apps/plugin-dialog'
 import { useAppStore } from '../store'
+import type { AppError, DbCandidate } from '../types'
 
-export default function SetupScreen() {
+export default function SetupScreen({ candidates }: { candidates: DbCandidate[] }) {
   const { setDbPath } = useAppStore()
   const [busy, setBusy] = useState(false)
   const [error, setError] = useState<string | null>(null)
 
+  const selectPath = async (path: string) => {
+    await invoke('set_joplin_db_path', { path })
+    setDbPath(path)
+  }
+
   const handleBrowse = async () => {
     if (busy) return
     setBusy(true)
@@ -15,13 +21,24 @@ export default function SetupScreen() {
     try {
       const result = await open({ filters: [{ name: 'SQLite', extensions: ['sqlite', 'db'] }], multiple: false })
       const path = typeof result === 'string' ? result : null
-      if (path) {
-        await invoke('set_joplin_db_path', { path })
-        setDbPath(path)
-      }
+      if (path) await selectPath(path)
+    } catch (err) {
+      console.error('Failed to set DB path:', err)
+      setError((err as AppError).message ?? 'Failed to open database.')
+    } finally {
+      setBusy(false)
+    }
+  }
+
+  const handleChoose = async (path: string) => {
+    if (busy) return
+    setBusy(true)
+    setError(null)
+    try {
+      await selectPath(path)
     } catch (err) {
       console.error('Failed to set DB path:', err)
-      setError(typeof err === 'string' ? err : 'Failed to open database.')
+      setError((err as AppError).message ?? 'Failed to open database.')
     } finally {
       setBusy(false)
     }
@@ -29,8 +46,38 @@ export default function SetupScreen() {
 
   return (
     <div className="flex h-screen w-full flex-col items-center justify-center bg-zinc-950 text-zinc-100">
-      <h1 className="mb-2 text-xl font-bold">Joplin database not found</h1>
-      <p className="mb-6 text-sm text-zinc-400">Locate your Joplin SQLite database to get started.</p>
+      {candidates.length > 0 ? (
+        <>
+          <h1 className="mb-2 text-xl font-bold">Choose a Joplin profile</h1>
+          <p className="mb-6 text-sm text-zinc-400">Several Joplin databases were found on this machine.</p>
+          <div className="mb-6 w-full max-w-md space-y-2">
+            {candidates.map((c) => (
+              <button
+                key={c.path}
+                type="button"
+                onClick={() => handleChoose(c.path)}
+                disabled={busy}
+                className="w-full rounded-lg border border-zinc-800 px-4 py-3 text-left hover:bg-zinc-800 disabled:opacity-50"
+              >
+                <h3 className="font-medium text-zinc-100">
+                  {c.profile_name}
+                  <span className="ml-2 text-xs font-normal text-zinc-500">{c.install}</span>
+                </h3>
+                <p className="text-xs text-zinc-400">
+                  {c.note_count !== null ? `${c.note_count} notes` : 'Unreadable'}
+                  {c.modified_time !== null && ` · modified ${new Date(c.modified_time).toLocaleDateString()}`}
+                </p>
+                <p className="truncate text-xs text-zinc-600">{c.path}</p>
+              </button>
+            ))}
+          </div>
+        </>
+      ) : (
+        <>
+          <h1 className="mb-2 text-xl font-bold">Joplin database not found</h1>
+          <p className="mb-6 text-sm text-zinc-400">Locate your Joplin SQLite database to get started.</p>
+        </>
+      )}
       <button
         onClick={handleBrowse}
         disabled={busy}
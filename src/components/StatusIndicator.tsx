@@ -3,18 +3,22 @@ import { useAppStore } from '../store'
 export default function StatusIndicator() {
   const { indexStatus } = useAppStore()
 
-  const { is_ready, is_downloading_model, error, total_notes } = indexStatus
+  const { phase, is_ready, error, total_notes, progress } = indexStatus
 
   let color: string
   let label: string
 
   if (error) {
     color = 'bg-red-500'
-    label = error
+    label = error.message
+  } else if (is_ready && phase === 'embedding' && progress !== null) {
+    // A rebuild runs while the previous index keeps serving searches
+    color = 'bg-green-500'
+    label = `Rebuilding index · ${Math.round(progress * 100)}%`
   } else if (is_ready) {
     color = 'bg-green-500'
     label = total_notes > 0 ? `Index ready · ${total_notes} notes` : 'Index active'
-  } else if (is_downloading_model) {
+  } else if (phase === 'downloading_model') {
     color = 'bg-yellow-500'
     label = 'Downloading model…'
   } else {
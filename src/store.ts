@@ -18,11 +18,15 @@ interface AppStore {
 export const useAppStore = create<AppStore>((set) => ({
   dbPath: null,
   indexStatus: {
+    phase: 'detecting',
     total_notes: 0,
     indexed_notes: 0,
     is_ready: false,
-    is_downloading_model: false,
-    download_progress: 0,
+    progress: null,
+    notes_per_sec: null,
+    eta_secs: null,
+    downloaded_bytes: 0,
+    download_total_bytes: null,
     error: null,
   },
   results: [],
@@ -3,24 +3,303 @@ export interface Note {
   title: string
   body: string
   updated_time: number
+  is_todo: boolean
+  todo_completed: number
+  is_conflict: boolean
+  in_trash: boolean
+  created_time: number
+  user_updated_time: number
+  source_url: string
+  author: string
+  latitude: number
+  longitude: number
+  markup_language: number
+  parent_id: string
 }
 
 export interface NoteMetadata {
   id: string
   title: string
   updated_time: number
+  is_todo: boolean
+  todo_completed: number
+  is_conflict: boolean
+  in_trash: boolean
+  created_time: number
+  user_updated_time: number
+  source_url: string
+  author: string
+  latitude: number
+  longitude: number
+  parent_id: string
+}
+
+export interface RevisionMetadata {
+  id: string
+  note_id: string
+  title: string
+  revision_time: number
 }
 
 export interface SearchResult {
   note: NoteMetadata
   score: number
+  revision: RevisionMetadata | null
+  ranking: RankingBreakdown | null
+  explanation: ScoreExplanation | null
+}
+
+export interface ScoreBoost {
+  signal: string
+  value: number
+}
+
+export interface ScoreExplanation {
+  source: 'note' | 'revision'
+  cosine_distance: number
+  matched_passage: string | null
+  passage_similarity: number | null
+  lexical_score: number | null
+  boosts: ScoreBoost[]
+  filters: string[]
+  rank_raw: number
+  rank_filtered: number
+  rank_final: number
+}
+
+export interface RankingBreakdown {
+  similarity: number
+  recency: number
+  usage: number
+  pinned: number
+}
+
+export interface SearchExample {
+  text?: string | null
+  note_ids?: string[]
+  negative_text?: string | null
+  negative_note_ids?: string[]
+}
+
+export interface HistoryEntry {
+  query: string
+  options: SearchOptions
+  time: number
+}
+
+export interface SavedSearch {
+  id: string
+  name: string
+  query: string
+  options: SearchOptions
+  created_time: number
+  alert_threshold: number | null
+  notify: boolean
 }
 
+export interface SavedSearchAlert {
+  search_id: string
+  name: string
+  query: string
+  matches: SearchResult[]
+}
+
+export type SortBy = 'relevance' | 'created_time' | 'updated_time'
+
+export interface SearchOptions {
+  source_domain?: string | null
+  author?: string | null
+  created_after?: number | null
+  created_before?: number | null
+  updated_after?: number | null
+  updated_before?: number | null
+  has_location?: boolean | null
+  sort_by?: SortBy
+  ascending?: boolean
+  explain?: boolean
+  mmr_lambda?: number | null
+}
+
+export type IndexPhase =
+  | 'detecting'
+  | 'downloading_model'
+  | 'loading_index'
+  | 'embedding'
+  | 'saving'
+  | 'ready'
+  | 'error'
+
 export interface IndexStatus {
+  phase: IndexPhase
   total_notes: number
   indexed_notes: number
   is_ready: boolean
-  is_downloading_model: boolean
-  download_progress: number
+  progress: number | null // 0.0 to 1.0, current phase
+  notes_per_sec: number | null
+  eta_secs: number | null
+  downloaded_bytes: number
+  download_total_bytes: number | null
+  error: AppError | null
+}
+
+export type ErrorCode =
+  | 'index_not_ready'
+  | 'model_not_loaded'
+  | 'index_busy'
+  | 'not_indexing'
+  | 'indexing_cancelled'
+  | 'db_not_configured'
+  | 'note_not_found'
+  | 'revision_not_found'
+  | 'saved_search_not_found'
+  | 'invalid_note_id'
+  | 'empty_example'
+  | 'empty_saved_search'
+  | 'only_http_allowed'
+  | 'limits_unsupported'
+  | 'database_error'
+  | 'inference_error'
+  | 'storage_error'
+  | 'watcher_error'
+  | 'launch_failed'
+  | 'internal_error'
+
+// Rejection value of every command, and error of background failures.
+export interface AppError {
+  code: ErrorCode
+  message: string
+  retryable: boolean // the same call may succeed later as is
+}
+
+// Payload of the `background-error` event and of get_error_log.
+export interface ErrorLogEntry {
+  time: number // Unix ms
+  source: string // e.g. 'delta_update', 'revision_indexing', 'maintenance'
+  error: AppError
+}
+
+export interface DbCandidate {
+  path: string
+  profile_id: string
+  profile_name: string
+  install: string
+  is_current_profile: boolean
+  note_count: number | null
+  modified_time: number | null
+}
+
+export interface Settings {
+  db_path: string | null
+  index_revisions: boolean
+  include_trash: boolean
+  include_conflicts: boolean
+  include_empty_todos: boolean
+  boost_linked_results: boolean
+  ranking: RankingWeights
+  disable_history: boolean
+  watcher_debounce_ms: number | null
+  // Turn on for a database on a network filesystem (NFS, SMB), which never
+  // sends change notifications: without it, edits are only picked up on restart.
+  watcher_polling: boolean
+  maintenance: MaintenanceSettings
+  inference_workers: number | null
+  resources: ResourceLimits
+}
+
+export interface ResourceLimits {
+  max_threads: number | null
+  batch_size: number
+  inference_slice: number
+  low_priority: boolean
+}
+
+export interface MaintenanceSettings {
+  paused: boolean
+  idle_minutes: number
+  compact_dead_ratio: number
+}
+
+export type MaintenanceTask = 'compact' | 'reembed' | 'rebuild'
+
+export interface MaintenanceStatus {
+  needed: MaintenanceTask | null
+  running: MaintenanceTask | null
+  last_task: MaintenanceTask | null
+  last_run: number | null
+  live_nodes: number
+  dead_nodes: number
+}
+
+export interface WatcherStatus {
+  mode: 'stopped' | 'events' | 'polling'
+  debounce_ms: number
+  pending: boolean
+  last_change: number | null
+  last_update: number | null
   error: string | null
-}
\ No newline at end of file
+}
+
+export interface RankingWeights {
+  recency: number
+  recency_half_life_days: number
+  usage: number
+  usage_half_life_days: number
+  pinned: number
+}
+
+export interface TopicMember {
+  note_id: string
+  title: string
+  notebook: string
+  similarity: number
+  outside_top_notebook: boolean
+}
+
+export interface TopicCluster {
+  id: number
+  keywords: string[]
+  representative_titles: string[]
+  top_notebook: string | null
+  members: TopicMember[]
+}
+
+export interface DuplicatePair {
+  a: string
+  b: string
+  similarity: number
+  text_similarity: number | null
+}
+
+export interface DuplicateNote {
+  note: NoteMetadata
+  notebook: string
+}
+
+export interface DuplicateGroup {
+  notes: DuplicateNote[]
+  pairs: DuplicatePair[]
+}
+
+export interface DuplicateOptions {
+  threshold?: number
+  text_check?: boolean
+  min_text_similarity?: number
+}
+
+export interface TagSuggestion {
+  tag: string
+  confidence: number
+}
+
+export interface NotebookSuggestion {
+  notebook_id: string
+  notebook: string
+  confidence: number
+}
+
+export interface NoteSuggestion {
+  note_id: string
+  title: string
+  tags: TagSuggestion[]
+  notebooks: NotebookSuggestion[]
+}